@@ -1,21 +1,94 @@
 use candid::{CandidType, Deserialize, Principal};
-use ic_cdk::api::{
-    management_canister::{
-        ecdsa::{ecdsa_public_key, sign_with_ecdsa, EcdsaCurve, EcdsaKeyId, EcdsaPublicKeyArgument, SignWithEcdsaArgument},
-    },
-};
-use ic_cdk::caller as msg_caller;
-use ic_cdk::api::time;
+#[cfg(not(test))]
+use ic_cdk::api::{canister_self, msg_caller, time};
+#[cfg(not(test))]
+use ic_cdk::call::Call;
+#[cfg(not(test))]
+use ic_cdk::futures::spawn;
+#[cfg(not(test))]
+use ic_cdk::management_canister::{canister_info, ecdsa_public_key, sign_with_ecdsa};
+use ic_cdk::management_canister::{CanisterInfoArgs, EcdsaCurve, EcdsaKeyId, EcdsaPublicKeyArgs, SignWithEcdsaArgs};
+#[cfg(not(test))]
+use ic_cdk::stable::stable_size;
 use ic_cdk_macros::{init, post_upgrade, pre_upgrade, query, update};
 use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
-use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, Storable};
+use ic_stable_structures::{Cell as StableCell, DefaultMemoryImpl, StableBTreeMap, Storable};
+use k256::ecdsa::{signature::hazmat::PrehashVerifier, Signature, VerifyingKey};
 use serde::Serialize;
 use sha2::{Digest, Sha256};
 use std::borrow::Cow;
 use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
+use std::future::Future;
+use std::ops::Bound;
+// The system API traps outside a canister, so unit tests swap in native stand-ins
+#[cfg(test)]
+use tests::ic::{
+    canister_info, canister_self, ecdsa_public_key, msg_caller, sign_with_ecdsa, spawn, stable_size, time, Call,
+};
 
 type Memory = VirtualMemory<DefaultMemoryImpl>;
 
+// Detached signing is rate-limited per caller to protect the threshold ECDSA budget
+const SIGNING_RATE_LIMIT_WINDOW_NS: u64 = 60_000_000_000;
+const MAX_SIGNING_REQUESTS_PER_WINDOW: usize = 10;
+
+// Upper bound on records verified per call to stay within the query instruction limit
+const MAX_SIGNATURE_BATCH_SIZE: u64 = 500;
+
+const MAX_COMPLIANCE_BATCH_SIZE: u64 = 100;
+
+const MAX_IMAGE_BYTES: usize = 50 * 1024 * 1024;
+
+const MODEL_VERSION: &str = "MedicalAI-v2.1.0";
+
+// Key names provisioned for local replicas and mainnet testing, never for clinical use
+const TEST_ECDSA_KEY_NAMES: [&str; 2] = ["dfx_test_key", "test_key_1"];
+
+const MAX_CONSENT_BATCH_SIZE: u64 = 100;
+
+const MAX_ROSTER_PAGE_SIZE: u64 = 1000;
+
+const MAX_QA_SAMPLE_SIZE: u64 = 1000;
+
+const MAX_CONSENSUS_MODELS: u64 = 16;
+
+const MAX_ARCHIVAL_BATCH_SIZE: u64 = 500;
+
+const MAX_SEVERITY_MIGRATION_BATCH_SIZE: u64 = 500;
+
+const MAX_RECENT_ERRORS: usize = 100;
+
+// Free-text inputs end up in audit entries and diagnosis records, which have bounded encodings
+const MAX_FREE_TEXT_BYTES: usize = 1024;
+
+// Dedup entries are otherwise only evicted on lookup, so each insert also sweeps a few expired ones
+const DEDUP_SWEEP_BATCH_SIZE: u64 = 16;
+const MAX_DEDUP_PRUNE_BATCH_SIZE: u64 = 1000;
+
+// Upper bound on a stored diagnosis record, after compression when enabled
+const MAX_DIAGNOSIS_BYTES: u32 = 8192;
+
+// Keyword in the lowercased diagnosis text, ICD-10-CM code, SNOMED CT concept ID
+const DIAGNOSIS_CODES: [(&str, &str, &str); 6] = [
+    ("normal", "Z03.89", "17621005"),
+    ("pneumonia", "J18.9", "233604007"),
+    ("pleural effusion", "J90", "60046008"),
+    ("cardiomegaly", "I51.7", "8186001"),
+    ("pulmonary edema", "J81.0", "19242006"),
+    ("pneumothorax", "J93.9", "36118008"),
+];
+
+const NANOS_PER_DAY: u64 = 86_400_000_000_000;
+
+// Version 1 records predate the version prefix in the signed payload
+const LEGACY_PAYLOAD_SCHEMA_VERSION: u16 = 1;
+const PAYLOAD_SCHEMA_VERSION: u16 = 2;
+
+// Growth rate for storage runway estimates is measured over this trailing window
+const STORAGE_GROWTH_WINDOW_DAYS: u64 = 30;
+
 // Medical AI Data Structures
 #[derive(CandidType, Serialize, Deserialize, Clone)]
 pub struct PatientMetadata {
@@ -25,12 +98,103 @@ pub struct PatientMetadata {
     pub acquisition_date: String,
 }
 
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Normal,
+    Mild,
+    Moderate,
+    Severe,
+    Critical,
+    Unknown,
+}
+
+impl Severity {
+    fn parse(severity: &str) -> Option<Self> {
+        match severity.trim().to_ascii_lowercase().as_str() {
+            "normal" => Some(Self::Normal),
+            "mild" => Some(Self::Mild),
+            "moderate" => Some(Self::Moderate),
+            "severe" => Some(Self::Severe),
+            "critical" => Some(Self::Critical),
+            "unknown" => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+
+    /// Ordering used when findings must be dropped; unmappable severities rank as `Unknown`.
+    fn priority(severity: &str) -> u8 {
+        match Self::parse(severity).unwrap_or(Self::Unknown) {
+            Self::Critical => 5,
+            Self::Severe => 4,
+            Self::Moderate => 3,
+            Self::Unknown => 2,
+            Self::Mild => 1,
+            Self::Normal => 0,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Normal => "Normal",
+            Self::Mild => "Mild",
+            Self::Moderate => "Moderate",
+            Self::Severe => "Severe",
+            Self::Critical => "Critical",
+            Self::Unknown => "Unknown",
+        }
+    }
+}
+
+/// What to do with a result whose findings push it past `MAX_DIAGNOSIS_BYTES`.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OversizedFindingsPolicy {
+    Reject,
+    /// Keep the most severe, then most confident, findings that fit.
+    TruncateToFit,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnmappedSeverityPolicy {
+    MapToUnknown,
+    /// Leave the original text untouched and record the diagnosis for manual review.
+    FlagForReview,
+}
+
 #[derive(CandidType, Serialize, Deserialize, Clone)]
 pub struct MedicalFinding {
     pub finding: String,
     pub location: String,
     pub severity: String,
     pub confidence: f32,
+    /// Model output before calibration; `None` on findings stored before calibration existed.
+    pub raw_confidence: Option<f32>,
+    /// Number of models reporting this finding, set on consolidated consensus findings.
+    pub agreement_count: Option<u32>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosisStatus {
+    Active,
+    UnderReview,
+    Amended,
+    Superseded,
+    Deleted,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReviewDecision {
+    Confirmed,
+    Disputed,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct ReviewRecord {
+    pub reviewer: Principal,
+    pub decision: ReviewDecision,
+    pub notes: String,
+    pub timestamp: u64,
+    pub review_signature: Vec<u8>,
+    pub review_public_key: Vec<u8>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone)]
@@ -38,6 +202,9 @@ pub struct MedicalDiagnosisResult {
     pub id: u64,
     pub diagnosis: String,
     pub confidence_score: f32,
+    pub raw_confidence_score: Option<f32>,
+    /// Calibrated confidence before any image-quality downgrade.
+    pub unadjusted_confidence_score: Option<f32>,
     pub medical_findings: Vec<MedicalFinding>,
     pub timestamp: u64,
     pub signature: Vec<u8>,
@@ -46,6 +213,43 @@ pub struct MedicalDiagnosisResult {
     pub hipaa_compliant: bool,
     pub model_version: String,
     pub patient_metadata: PatientMetadata,
+    pub status: Option<DiagnosisStatus>,
+    pub review: Option<ReviewRecord>,
+    pub amends: Option<u64>,
+    pub source_id: Option<u64>,
+    pub assigned_reviewer: Option<Principal>,
+    /// Confidence in basis points (0-10000), set when the score was signed in scaled form.
+    pub confidence_bps: Option<u16>,
+    pub icd10_code: Option<String>,
+    pub snomed_code: Option<String>,
+    /// Principal that produced the record; `None` for imports.
+    pub submitted_by: Option<Principal>,
+    pub metrics: Option<ImageAnalysisMetrics>,
+    /// Canonicalization used when the record was signed; see `diagnosis_signing_payload`.
+    pub payload_schema_version: Option<u16>,
+    pub signing_curve: Option<SigningCurve>,
+}
+
+// Fields added after the first release are optional so that records stored before them
+// still decode; these accessors supply the values such records implicitly had.
+impl MedicalDiagnosisResult {
+    fn status(&self) -> DiagnosisStatus {
+        self.status.unwrap_or(DiagnosisStatus::Active)
+    }
+
+    fn payload_schema_version(&self) -> u16 {
+        self.payload_schema_version.unwrap_or(LEGACY_PAYLOAD_SCHEMA_VERSION)
+    }
+
+    fn signing_curve(&self) -> SigningCurve {
+        self.signing_curve.unwrap_or(SigningCurve::Secp256k1)
+    }
+}
+
+impl MedicalFinding {
+    fn raw_confidence(&self) -> f32 {
+        self.raw_confidence.unwrap_or(self.confidence)
+    }
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone)]
@@ -57,6 +261,11 @@ pub struct MedicalAuditEntry {
     pub principal_id: Principal,
     pub details: String,
     pub compliance_flags: Vec<String>,
+    pub entry_signature: Option<Vec<u8>>,
+    pub entry_public_key: Option<Vec<u8>>,
+    /// Chain hash of the preceding entry; empty for the first chained entry and `None` on
+    /// entries written before the chain existed.
+    pub previous_hash: Option<Vec<u8>>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone)]
@@ -71,30 +280,428 @@ pub struct ComplianceReport {
     pub generated_timestamp: u64,
 }
 
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum MedicalError {
+    Unauthorized,
+    RateLimited,
+    SignatureFailed(String),
+    DiagnosisNotFound,
+    InvalidStatusTransition(DiagnosisStatus),
+    InvalidImage(String),
+    InvalidAcquisitionDate,
+    InvalidConfig(String),
+    InvalidSignature,
+    BatchTooLarge(u64),
+    AnonymousCaller,
+    SigningDisabled,
+    ReviewNotFound,
+    InvalidConsent(String),
+    AuditEntryNotFound,
+    InconsistentFindings(String),
+    WeakAnonymization,
+    UnregisteredModel(String),
+    NotNormalDiagnosis,
+    ManagementCallFailed(String),
+    UploadNotFound,
+    TooManyUploads,
+    InvalidDateFormat,
+    AuditEntryImmutable,
+    ResultTooLarge,
+    /// The record changed while this call awaited signing; retry against the current state.
+    ConcurrentModification,
+    /// A free-text argument exceeds the given maximum length in bytes.
+    TextTooLong(u64),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct CanisterConfig {
+    /// Off by default, since enabling it also rejects any `acquisition_date` that is not
+    /// `YYYY-MM-DD` or `YYYY-MM` once `accepted_date_formats` normalization has run.
+    pub reject_future_acquisition_dates: bool,
+    pub acquisition_date_max_skew_ns: u64,
+    /// Findings below this confidence are hidden from query responses only;
+    /// stored and signed records always keep the full findings list.
+    pub output_finding_confidence_floor: Option<f32>,
+    pub confidence_calibration: Option<ConfidenceCalibration>,
+    pub signing_enabled: bool,
+    pub ecdsa_key_name: String,
+    pub signing_curve: SigningCurve,
+    /// Derive a per-patient diagnosis signing key from the anonymized ID instead of the root key.
+    pub per_patient_key_derivation: bool,
+    pub retention_period_days: Option<u64>,
+    /// Logs read access such as compliance report generation; mutations are always audited.
+    pub access_logging_enabled: bool,
+    /// Signs each audit entry in the background so it can be verified in isolation.
+    pub sign_audit_entries: bool,
+    pub findings_consistency_policy: FindingsConsistencyPolicy,
+    pub storage_capacity_bytes: u64,
+    /// Window in which re-submitting the same image for the same patient returns the
+    /// existing diagnosis instead of re-analyzing; 0 disables deduplication.
+    pub dedup_ttl_ns: u64,
+    pub anonymization_policy: Option<AnonymizationPolicy>,
+    /// Rejects analyses and imports whose model version is not registered and active.
+    pub enforce_registered_model: bool,
+    /// Deflate-compresses diagnoses written from now on; existing records are read either way.
+    pub compress_diagnoses: bool,
+    pub quality_downgrade: Option<QualityDowngradePolicy>,
+    pub max_upload_sessions_per_principal: u32,
+    pub unmapped_severity_policy: UnmappedSeverityPolicy,
+    /// Providers that flagged diagnoses are assigned to, round-robin. Empty leaves them unassigned.
+    pub on_call_reviewers: Vec<Principal>,
+    /// Report diagnoses without a verifiable signature as FDA non-compliant.
+    pub require_signatures_for_compliance: bool,
+    /// Sign confidences as integer basis points so the payload is platform-independent.
+    pub scaled_confidence: bool,
+    /// Accepted `acquisition_date` formats. Empty accepts any string without normalizing it.
+    pub accepted_date_formats: Vec<DateFormat>,
+    /// How long the newest audit entry may be amended before it is treated as chained.
+    /// Zero makes every entry immutable on write.
+    pub audit_amendment_grace_ns: u64,
+    /// Once a diagnosis has this many audit entries, repeats of the same action within
+    /// `audit_coalesce_window_ns` are suppressed behind a single marker entry.
+    pub max_audit_entries_per_diagnosis: Option<u64>,
+    pub audit_coalesce_window_ns: u64,
+    /// Target canisters notified of new findings by severity. Unmapped severities are not sent.
+    pub escalation_channels: Vec<EscalationChannel>,
+    /// Age after which `archive_old_diagnoses` moves records out of the hot map.
+    pub archive_after_days: Option<u64>,
+    pub oversized_findings_policy: OversizedFindingsPolicy,
+}
+
+/// Threshold ECDSA curve for canister signatures. The management canister currently only
+/// offers secp256k1; secp256r1 is accepted here so records and config can name it, but
+/// `update_config` rejects it until the IC supports it.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SigningCurve {
+    Secp256k1,
+    Secp256r1,
+}
+
+impl SigningCurve {
+    fn ecdsa_curve(&self) -> Result<EcdsaCurve, String> {
+        match self {
+            Self::Secp256k1 => Ok(EcdsaCurve::Secp256k1),
+            Self::Secp256r1 => Err("secp256r1 threshold ECDSA is not available on the IC".to_string()),
+        }
+    }
+
+    /// Identifies the signing algorithm in exported verification bundles.
+    fn signature_scheme(&self) -> &'static str {
+        match self {
+            Self::Secp256k1 => "ecdsa-secp256k1-sha256",
+            Self::Secp256r1 => "ecdsa-secp256r1-sha256",
+        }
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateFormat {
+    /// `YYYY-MM-DD`, also the canonical stored form for full dates.
+    IsoDate,
+    /// DICOM DA, `YYYYMMDD`.
+    DicomDate,
+    /// `YYYY-MM`, stored as is.
+    YearMonth,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FindingsConsistencyPolicy {
+    Off,
+    /// Store the result but place it under review with an audit entry.
+    Flag,
+    Reject,
+}
+
+/// Minimum strength for `anonymized_id`. Entropy is estimated as the Shannon entropy of the
+/// ID's character distribution multiplied by its length, so "patient1" scores ~22 bits while
+/// a random 32-character hex string scores roughly 125.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy)]
+pub struct AnonymizationPolicy {
+    pub min_length: u32,
+    pub min_entropy_bits: f32,
+}
+
+/// Scales `confidence_score` down when `quality_score` falls below `quality_threshold`,
+/// by `strength * (quality_threshold - quality_score) / quality_threshold`.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy)]
+pub struct QualityDowngradePolicy {
+    pub quality_threshold: f32,
+    pub strength: f32,
+}
+
+/// Platt scaling parameters applied in logit space: `sigmoid(slope * logit(p) + intercept)`.
+/// Temperature scaling is the special case `slope = 1 / T`, `intercept = 0`.
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy)]
+pub struct ConfidenceCalibration {
+    pub slope: f32,
+    pub intercept: f32,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct EscalationChannel {
+    pub severity: Severity,
+    pub target: Principal,
+}
+
+/// Sent one-way to `notify_escalation : (EscalationNotice) -> ()` on escalation targets.
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct EscalationNotice {
+    pub diagnosis_id: u64,
+    pub severity: Severity,
+    pub findings: Vec<String>,
+}
+
+impl Default for CanisterConfig {
+    fn default() -> Self {
+        Self {
+            reject_future_acquisition_dates: false,
+            // Dates carry no timezone, so allow a full day for sites ahead of UTC
+            acquisition_date_max_skew_ns: NANOS_PER_DAY,
+            output_finding_confidence_floor: None,
+            confidence_calibration: None,
+            signing_enabled: true,
+            ecdsa_key_name: "dfx_test_key".to_string(),
+            signing_curve: SigningCurve::Secp256k1,
+            per_patient_key_derivation: false,
+            retention_period_days: None,
+            access_logging_enabled: true,
+            sign_audit_entries: false,
+            findings_consistency_policy: FindingsConsistencyPolicy::Flag,
+            // Maximum stable memory a single canister may address
+            storage_capacity_bytes: 500 * 1024 * 1024 * 1024,
+            dedup_ttl_ns: NANOS_PER_DAY,
+            anonymization_policy: None,
+            enforce_registered_model: false,
+            compress_diagnoses: false,
+            quality_downgrade: None,
+            max_upload_sessions_per_principal: 3,
+            unmapped_severity_policy: UnmappedSeverityPolicy::FlagForReview,
+            on_call_reviewers: Vec::new(),
+            require_signatures_for_compliance: false,
+            scaled_confidence: false,
+            accepted_date_formats: Vec::new(),
+            audit_amendment_grace_ns: 0,
+            max_audit_entries_per_diagnosis: None,
+            audit_coalesce_window_ns: 60 * 60 * 1_000_000_000,
+            escalation_channels: Vec::new(),
+            archive_after_days: None,
+            oversized_findings_policy: OversizedFindingsPolicy::Reject,
+        }
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsentScope {
+    Diagnosis,
+    Research,
+    DataSharing,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct ConsentRecord {
+    pub anonymized_id: String,
+    /// Hex-encoded SHA-256 of the signed consent document held off-chain.
+    pub consent_hash: String,
+    pub scopes: Vec<ConsentScope>,
+    pub granted_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct ModelRecord {
+    pub version: String,
+    pub description: String,
+    pub fda_cleared: bool,
+    pub active: bool,
+    pub registered_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct ComplianceCheck {
+    pub name: String,
+    pub passed: bool,
+    pub remediation: Option<String>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct ComplianceSelfAudit {
+    pub passed: bool,
+    pub checks: Vec<ComplianceCheck>,
+    pub generated_timestamp: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct DedupEntry {
+    pub diagnosis_id: u64,
+    pub created_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct ConfigChange {
+    pub id: u64,
+    pub timestamp: u64,
+    pub admin: Principal,
+    pub old_config: CanisterConfig,
+    pub new_config: CanisterConfig,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct VerificationBundle {
+    pub diagnosis: MedicalDiagnosisResult,
+    pub signature: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub canonical_payload: Vec<u8>,
+    pub canister_id: Principal,
+    pub signature_scheme: String,
+    pub exported_at: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct SignatureBatchResult {
+    pub verified: u64,
+    pub failed: u64,
+    pub failed_ids: Vec<u64>,
+    pub next_cursor: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SeverityMigrationResult {
+    pub migrated: u64,
+    pub next_cursor: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct RecordTypeStats {
+    pub count: u64,
+    pub total_bytes: u64,
+    pub average_bytes: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct StorageStats {
+    pub diagnoses: RecordTypeStats,
+    pub audit_entries: RecordTypeStats,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct StorageRunway {
+    pub used_bytes: u64,
+    pub capacity_bytes: u64,
+    pub growth_bytes_per_day: u64,
+    pub estimated_days_to_full: Option<u64>,
+    pub estimated_full_timestamp: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct AccessOverview {
+    pub controllers: Vec<Principal>,
+    pub admins: Vec<Principal>,
+    pub providers: Vec<Principal>,
+    /// In-canister admins that are not IC controllers, flagged for access review.
+    pub admins_not_controllers: Vec<Principal>,
+}
+
+/// Heap entry ordered by confidence, then ID, so the max-heap evicts the most confident.
+#[derive(PartialEq)]
+struct ConfidenceRank {
+    confidence: f32,
+    id: u64,
+}
+
+impl Eq for ConfidenceRank {}
+
+impl Ord for ConfidenceRank {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.confidence.total_cmp(&other.confidence).then(self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for ConfidenceRank {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+struct UploadSession {
+    owner: Principal,
+    data: Vec<u8>,
+}
+
 #[derive(CandidType, Serialize, Deserialize, Clone)]
 pub struct ImageAnalysisMetrics {
     pub image_size_kb: u32,
+    pub image_size_bytes: u64,
     pub processing_time_ms: u64,
     pub model_inference_time_ms: u64,
     pub preprocessing_time_ms: u64,
     pub quality_score: f32,
 }
 
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct TimelineEntry {
+    pub diagnosis: MedicalDiagnosisResult,
+    /// Nanoseconds since the patient's previous study; `None` for the first.
+    pub since_previous_ns: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct ErrorEvent {
+    pub timestamp: u64,
+    pub endpoint: String,
+    pub error: MedicalError,
+    pub caller: Principal,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct LatencyPercentiles {
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct ProcessingTimePercentiles {
+    pub sample_count: u64,
+    pub model_inference_time: LatencyPercentiles,
+    pub processing_time: LatencyPercentiles,
+}
+
 // Stable Storage Implementation
+// Uncompressed records are plain Candid and start with its magic bytes; compressed
+// records start with a format byte instead, so both can coexist in the same map.
+const CANDID_MAGIC: &[u8] = b"DIDL";
+const DIAGNOSIS_FORMAT_DEFLATE: u8 = 1;
+
 impl Storable for MedicalDiagnosisResult {
-    fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).unwrap())
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let encoded = candid::encode_one(self).unwrap();
+        if !CONFIG.with(|config| config.borrow().get().compress_diagnoses) {
+            return Cow::Owned(encoded);
+        }
+
+        let mut bytes = vec![DIAGNOSIS_FORMAT_DEFLATE];
+        bytes.extend(miniz_oxide::deflate::compress_to_vec(&encoded, 6));
+        Cow::Owned(bytes)
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
+        if bytes.starts_with(CANDID_MAGIC) {
+            return candid::decode_one(&bytes).unwrap();
+        }
+
+        match bytes.split_first() {
+            Some((&DIAGNOSIS_FORMAT_DEFLATE, compressed)) => {
+                let encoded = miniz_oxide::inflate::decompress_to_vec(compressed).unwrap();
+                candid::decode_one(&encoded).unwrap()
+            }
+            _ => panic!("Unknown diagnosis storage format"),
+        }
     }
 
-    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded { max_size: 8192, is_fixed_size: false };
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded { max_size: MAX_DIAGNOSIS_BYTES, is_fixed_size: false };
 }
 
 impl Storable for MedicalAuditEntry {
-    fn to_bytes(&self) -> Cow<[u8]> {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
         Cow::Owned(candid::encode_one(self).unwrap())
     }
 
@@ -105,6 +712,66 @@ impl Storable for MedicalAuditEntry {
     const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Bounded { max_size: 4096, is_fixed_size: false };
 }
 
+impl Storable for CanisterConfig {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
+impl Storable for ConfigChange {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
+impl Storable for ConsentRecord {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
+impl Storable for DedupEntry {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
+impl Storable for ModelRecord {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(candid::encode_one(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: ic_stable_structures::storable::Bound = ic_stable_structures::storable::Bound::Unbounded;
+}
+
 // Global State
 thread_local! {
     static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
@@ -120,8 +787,86 @@ thread_local! {
             MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(1)))
         ));
 
-    static NEXT_DIAGNOSIS_ID: RefCell<u64> = RefCell::new(1);
-    static NEXT_AUDIT_ID: RefCell<u64> = RefCell::new(1);
+    static ADMINS: RefCell<StableBTreeMap<Principal, (), Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(2)))
+        ));
+
+    static PROVIDERS: RefCell<StableBTreeMap<Principal, (), Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(3)))
+        ));
+
+    static CONFIG: RefCell<StableCell<CanisterConfig, Memory>> =
+        RefCell::new(StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(4))),
+            CanisterConfig::default(),
+        ).expect("Failed to initialize canister config"));
+
+    static CONFIG_HISTORY: RefCell<StableBTreeMap<u64, ConfigChange, Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(5)))
+        ));
+
+    static MODEL_REGISTRY: RefCell<StableBTreeMap<String, ModelRecord, Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(6)))
+        ));
+
+    static CONSENTS: RefCell<StableBTreeMap<String, ConsentRecord, Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(7)))
+        ));
+
+    // Keyed by "<anonymized_id>:<sha256 of image>" so results are never shared across patients
+    static IMAGE_DEDUP: RefCell<StableBTreeMap<String, DedupEntry, Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(8)))
+        ));
+
+    static UNMAPPED_SEVERITIES: RefCell<StableBTreeMap<u64, (), Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(9)))
+        ));
+
+    // Tombstones for deleted diagnoses, so their audit entries are not mistaken for orphans
+    static DELETED_DIAGNOSES: RefCell<StableBTreeMap<u64, (), Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(10)))
+        ));
+
+    static AUDIT_ENTRY_COUNTS: RefCell<StableBTreeMap<u64, u64, Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(11)))
+        ));
+
+    // Aged diagnoses, kept out of DIAGNOSES so hot-path scans stay small
+    static ARCHIVED_DIAGNOSES: RefCell<StableBTreeMap<u64, MedicalDiagnosisResult, Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(12)))
+        ));
+
+    // Persisted so IDs, and the tombstones keyed by them, are never reissued after an upgrade
+    static NEXT_DIAGNOSIS_ID: RefCell<StableCell<u64, Memory>> =
+        RefCell::new(StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(13))),
+            1,
+        ).expect("Failed to initialize diagnosis ID counter"));
+
+
+    // Upload sessions are transient and intentionally not preserved across upgrades
+    static UPLOAD_SESSIONS: RefCell<HashMap<u64, UploadSession>> = RefCell::new(HashMap::new());
+    static NEXT_UPLOAD_ID: RefCell<u64> = const { RefCell::new(1) };
+
+    static NEXT_ON_CALL_INDEX: RefCell<usize> = const { RefCell::new(0) };
+
+    // Resume point for the incremental dedup sweep; restarting from the beginning is harmless
+    static DEDUP_SWEEP_CURSOR: RefCell<Option<String>> = const { RefCell::new(None) };
+
+    // Debugging aid only, so it lives on the heap and resets on upgrade
+    static RECENT_ERRORS: RefCell<VecDeque<ErrorEvent>> = const { RefCell::new(VecDeque::new()) };
+
+    static SIGNING_REQUESTS: RefCell<HashMap<Principal, Vec<u64>>> = RefCell::new(HashMap::new());
 }
 
 // Medical AI Model Implementation
@@ -141,12 +886,16 @@ fn analyze_chest_xray(image_data: &[u8]) -> (String, f32, Vec<MedicalFinding>) {
                     location: "Bilateral".to_string(),
                     severity: "Normal".to_string(),
                     confidence: 0.94,
+                    raw_confidence: Some(0.94),
+                    agreement_count: None,
                 },
                 MedicalFinding {
                     finding: "Normal cardiac silhouette".to_string(),
                     location: "Mediastinum".to_string(),
                     severity: "Normal".to_string(),
                     confidence: 0.89,
+                    raw_confidence: Some(0.89),
+                    agreement_count: None,
                 },
             ]
         ),
@@ -159,12 +908,16 @@ fn analyze_chest_xray(image_data: &[u8]) -> (String, f32, Vec<MedicalFinding>) {
                     location: "Right lower lobe".to_string(),
                     severity: "Moderate".to_string(),
                     confidence: 0.87,
+                    raw_confidence: Some(0.87),
+                    agreement_count: None,
                 },
                 MedicalFinding {
                     finding: "Air bronchograms".to_string(),
                     location: "Right lower lobe".to_string(),
                     severity: "Mild".to_string(),
                     confidence: 0.73,
+                    raw_confidence: Some(0.73),
+                    agreement_count: None,
                 },
             ]
         ),
@@ -177,6 +930,8 @@ fn analyze_chest_xray(image_data: &[u8]) -> (String, f32, Vec<MedicalFinding>) {
                     location: "Right lateral".to_string(),
                     severity: "Mild".to_string(),
                     confidence: 0.78,
+                    raw_confidence: Some(0.78),
+                    agreement_count: None,
                 },
             ]
         ),
@@ -189,6 +944,8 @@ fn analyze_chest_xray(image_data: &[u8]) -> (String, f32, Vec<MedicalFinding>) {
                     location: "Mediastinum".to_string(),
                     severity: "Moderate".to_string(),
                     confidence: 0.85,
+                    raw_confidence: Some(0.85),
+                    agreement_count: None,
                 },
             ]
         ),
@@ -201,12 +958,16 @@ fn analyze_chest_xray(image_data: &[u8]) -> (String, f32, Vec<MedicalFinding>) {
                     location: "Bilateral perihilar".to_string(),
                     severity: "Severe".to_string(),
                     confidence: 0.91,
+                    raw_confidence: Some(0.91),
+                    agreement_count: None,
                 },
                 MedicalFinding {
                     finding: "Kerley B lines".to_string(),
                     location: "Bilateral lower lobes".to_string(),
                     severity: "Moderate".to_string(),
                     confidence: 0.82,
+                    raw_confidence: Some(0.82),
+                    agreement_count: None,
                 },
             ]
         ),
@@ -219,12 +980,16 @@ fn analyze_chest_xray(image_data: &[u8]) -> (String, f32, Vec<MedicalFinding>) {
                     location: "Left upper lobe".to_string(),
                     severity: "Moderate".to_string(),
                     confidence: 0.89,
+                    raw_confidence: Some(0.89),
+                    agreement_count: None,
                 },
                 MedicalFinding {
                     finding: "Lung collapse".to_string(),
                     location: "Left upper lobe".to_string(),
                     severity: "Moderate".to_string(),
                     confidence: 0.84,
+                    raw_confidence: Some(0.84),
+                    agreement_count: None,
                 },
             ]
         )
@@ -233,18 +998,149 @@ fn analyze_chest_xray(image_data: &[u8]) -> (String, f32, Vec<MedicalFinding>) {
     (diagnosis, confidence, findings)
 }
 
-fn validate_medical_image(image_data: &[u8]) -> Result<ImageAnalysisMetrics, String> {
-    if image_data.len() < 1024 {
-        return Err("Image file too small - minimum 1KB required".to_string());
+fn calibrate_confidence(confidence: f32, calibration: &ConfidenceCalibration) -> f32 {
+    // Keep the logit finite for saturated model outputs
+    let p = confidence.clamp(1e-6, 1.0 - 1e-6);
+    let logit = (p / (1.0 - p)).ln();
+    let scaled = calibration.slope * logit + calibration.intercept;
+    1.0 / (1.0 + (-scaled).exp())
+}
+
+fn apply_calibration(
+    confidence_score: f32,
+    findings: &mut [MedicalFinding],
+    config: &CanisterConfig,
+) -> f32 {
+    let Some(calibration) = config.confidence_calibration else {
+        return confidence_score;
+    };
+    for finding in findings.iter_mut() {
+        finding.confidence = calibrate_confidence(finding.raw_confidence(), &calibration);
     }
-    
-    if image_data.len() > 50 * 1024 * 1024 {
-        return Err("Image file too large - maximum 50MB allowed".to_string());
+    calibrate_confidence(confidence_score, &calibration)
+}
+
+/// Rewrites finding severities to their canonical spelling. Returns true when some severity
+/// could not be mapped and was left for manual review under `FlagForReview`.
+fn normalize_severities(findings: &mut [MedicalFinding], policy: UnmappedSeverityPolicy) -> bool {
+    let mut needs_review = false;
+    for finding in findings.iter_mut() {
+        match (Severity::parse(&finding.severity), policy) {
+            (Some(severity), _) => finding.severity = severity.as_str().to_string(),
+            (None, UnmappedSeverityPolicy::MapToUnknown) => {
+                finding.severity = Severity::Unknown.as_str().to_string();
+            }
+            (None, UnmappedSeverityPolicy::FlagForReview) => needs_review = true,
+        }
     }
+    needs_review
+}
 
-    // Simulate image validation and quality assessment
-    Ok(ImageAnalysisMetrics {
+/// Keeps findings reported by a majority of models, matched on finding and location.
+/// Confidences are averaged across the reporting models and the severity is taken from
+/// the most confident report. Results are ordered by agreement, then confidence.
+fn consolidate_findings(per_model_findings: &[Vec<MedicalFinding>]) -> Vec<MedicalFinding> {
+    let key = |finding: &MedicalFinding| {
+        (finding.finding.trim().to_lowercase(), finding.location.trim().to_lowercase())
+    };
+
+    let mut reports: BTreeMap<(String, String), Vec<&MedicalFinding>> = BTreeMap::new();
+    for findings in per_model_findings {
+        // A model repeating a finding still counts once, using its most confident report
+        let mut per_model: BTreeMap<(String, String), &MedicalFinding> = BTreeMap::new();
+        for finding in findings {
+            per_model
+                .entry(key(finding))
+                .and_modify(|best| {
+                    if finding.confidence > best.confidence {
+                        *best = finding;
+                    }
+                })
+                .or_insert(finding);
+        }
+        for (key, finding) in per_model {
+            reports.entry(key).or_default().push(finding);
+        }
+    }
+
+    let mut consolidated: Vec<MedicalFinding> = reports
+        .into_values()
+        .filter(|reports| reports.len() * 2 > per_model_findings.len())
+        .map(|reports| {
+            let count = reports.len() as f32;
+            let most_confident = reports.iter().max_by(|a, b| a.confidence.total_cmp(&b.confidence)).unwrap();
+            MedicalFinding {
+                confidence: reports.iter().map(|f| f.confidence).sum::<f32>() / count,
+                raw_confidence: Some(reports.iter().map(|f| f.raw_confidence()).sum::<f32>() / count),
+                agreement_count: Some(reports.len() as u32),
+                ..(*most_confident).clone()
+            }
+        })
+        .collect();
+
+    consolidated.sort_by(|a, b| {
+        b.agreement_count.cmp(&a.agreement_count).then(b.confidence.total_cmp(&a.confidence))
+    });
+    consolidated
+}
+
+/// ICD-10 and SNOMED codes for a diagnosis, or `None` when it matches no coded category.
+fn diagnosis_codes(diagnosis: &str) -> Option<(String, String)> {
+    let diagnosis = diagnosis.to_lowercase();
+    DIAGNOSIS_CODES
+        .iter()
+        // Match at word starts so "abnormal" does not code as "normal"
+        .find(|(keyword, _, _)| diagnosis.starts_with(keyword) || diagnosis.contains(&format!(" {}", keyword)))
+        .map(|(_, icd10, snomed)| (icd10.to_string(), snomed.to_string()))
+}
+
+fn is_normal_diagnosis(diagnosis: &str) -> bool {
+    diagnosis.trim_start().to_lowercase().starts_with("normal")
+}
+
+/// Describes the contradiction between the diagnosis category and finding severities, if any.
+fn findings_contradiction(diagnosis: &str, findings: &[MedicalFinding]) -> Option<String> {
+    let abnormal: Vec<&MedicalFinding> = findings
+        .iter()
+        .filter(|finding| Severity::parse(&finding.severity) != Some(Severity::Normal))
+        .collect();
+
+    if is_normal_diagnosis(diagnosis) {
+        abnormal.first().map(|finding| {
+            format!("Normal diagnosis carries {} finding '{}'", finding.severity, finding.finding)
+        })
+    } else if !findings.is_empty() && abnormal.is_empty() {
+        Some("Abnormal diagnosis is supported only by Normal findings".to_string())
+    } else {
+        None
+    }
+}
+
+fn apply_quality_downgrade(confidence_score: f32, quality_score: f32, config: &CanisterConfig) -> f32 {
+    let Some(policy) = config.quality_downgrade else {
+        return confidence_score;
+    };
+    if policy.quality_threshold <= 0.0 || quality_score >= policy.quality_threshold {
+        return confidence_score;
+    }
+
+    let shortfall = (policy.quality_threshold - quality_score) / policy.quality_threshold;
+    confidence_score * (1.0 - policy.strength.clamp(0.0, 1.0) * shortfall).max(0.0)
+}
+
+fn validate_medical_image(image_data: &[u8]) -> Result<ImageAnalysisMetrics, String> {
+    if image_data.len() < 1024 {
+        return Err("Image file too small - minimum 1KB required".to_string());
+    }
+    
+    if image_data.len() > MAX_IMAGE_BYTES {
+        return Err("Image file too large - maximum 50MB allowed".to_string());
+    }
+
+    // Simulate image validation and quality assessment
+    Ok(ImageAnalysisMetrics {
         image_size_kb: (image_data.len() / 1024) as u32,
+        image_size_bytes: image_data.len() as u64,
         processing_time_ms: 1250,
         model_inference_time_ms: 850,
         preprocessing_time_ms: 400,
@@ -252,241 +1148,4881 @@ fn validate_medical_image(image_data: &[u8]) -> Result<ImageAnalysisMetrics, Str
     })
 }
 
-async fn create_cryptographic_signature(data: &str) -> Result<(Vec<u8>, Vec<u8>), String> {
-    let key_id = EcdsaKeyId {
-        curve: EcdsaCurve::Secp256k1,
-        name: "dfx_test_key".to_string(),
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses a canonical `YYYY-MM-DD` date, or `YYYY-MM` as the first of the month.
+fn parse_acquisition_date(date: &str) -> Option<u64> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = match parts.next() {
+        Some(day) => day.parse().ok()?,
+        None => 1,
     };
 
-    // Get public key
-    let public_key_result = ecdsa_public_key(EcdsaPublicKeyArgument {
+    if !(1970..=9999).contains(&year) || !(1..=12).contains(&month) {
+        return None;
+    }
+    if !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    // Days-from-civil conversion for the proleptic Gregorian calendar
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    u64::try_from(days).ok()?.checked_mul(NANOS_PER_DAY)
+}
+
+/// Rewrites `date` in its canonical form if it matches `format`.
+fn normalize_date(date: &str, format: DateFormat) -> Option<String> {
+    let bytes = date.as_bytes();
+    let digits = |range: std::ops::Range<usize>| bytes[range].iter().all(u8::is_ascii_digit);
+
+    let (year, month, day) = match format {
+        DateFormat::IsoDate if bytes.len() == 10 && bytes[4] == b'-' && bytes[7] == b'-'
+            && digits(0..4) && digits(5..7) && digits(8..10) => (&date[0..4], &date[5..7], Some(&date[8..10])),
+        DateFormat::DicomDate if bytes.len() == 8 && digits(0..8) => (&date[0..4], &date[4..6], Some(&date[6..8])),
+        DateFormat::YearMonth if bytes.len() == 7 && bytes[4] == b'-' && digits(0..4) && digits(5..7) => {
+            (&date[0..4], &date[5..7], None)
+        }
+        _ => return None,
+    };
+
+    let month_valid = (1..=12).contains(&month.parse::<u8>().ok()?);
+    let day_valid = day.is_none_or(|day| day.parse::<u8>().is_ok_and(|day| (1..=31).contains(&day)));
+    if !month_valid || !day_valid {
+        return None;
+    }
+
+    Some(match day {
+        Some(day) => format!("{}-{}-{}", year, month, day),
+        None => format!("{}-{}", year, month),
+    })
+}
+
+fn normalize_acquisition_date(metadata: &mut PatientMetadata, config: &CanisterConfig) -> Result<(), MedicalError> {
+    if config.accepted_date_formats.is_empty() {
+        return Ok(());
+    }
+
+    let date = metadata.acquisition_date.trim();
+    metadata.acquisition_date = config.accepted_date_formats
+        .iter()
+        .find_map(|format| normalize_date(date, *format))
+        .ok_or(MedicalError::InvalidDateFormat)?;
+
+    Ok(())
+}
+
+fn validate_acquisition_date(metadata: &PatientMetadata, config: &CanisterConfig, now: u64) -> Result<(), MedicalError> {
+    if !config.reject_future_acquisition_dates {
+        return Ok(());
+    }
+
+    let acquired_at = parse_acquisition_date(&metadata.acquisition_date)
+        .ok_or(MedicalError::InvalidAcquisitionDate)?;
+
+    if acquired_at > now.saturating_add(config.acquisition_date_max_skew_ns) {
+        return Err(MedicalError::InvalidAcquisitionDate);
+    }
+
+    Ok(())
+}
+
+fn anonymized_id_entropy_bits(anonymized_id: &str) -> f32 {
+    let chars: Vec<char> = anonymized_id.chars().collect();
+    if chars.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in &chars {
+        *counts.entry(*c).or_default() += 1;
+    }
+    let len = chars.len() as f32;
+    let bits_per_char: f32 = counts
+        .values()
+        .map(|&count| {
+            let p = count as f32 / len;
+            -p * p.log2()
+        })
+        .sum();
+
+    bits_per_char * len
+}
+
+fn is_active_model(version: &str) -> bool {
+    MODEL_REGISTRY.with(|registry| {
+        registry.borrow().get(&version.to_string()).is_some_and(|model| model.active)
+    })
+}
+
+fn validate_model_version(version: &str, config: &CanisterConfig) -> Result<(), MedicalError> {
+    if config.enforce_registered_model && !is_active_model(version) {
+        return Err(MedicalError::UnregisteredModel(version.to_string()));
+    }
+    Ok(())
+}
+
+fn validate_anonymization(metadata: &PatientMetadata, config: &CanisterConfig) -> Result<(), MedicalError> {
+    let Some(policy) = config.anonymization_policy else {
+        return Ok(());
+    };
+
+    let anonymized_id = metadata.anonymized_id.trim();
+    if anonymized_id.chars().count() < policy.min_length as usize
+        || anonymized_id_entropy_bits(anonymized_id) < policy.min_entropy_bits
+    {
+        return Err(MedicalError::WeakAnonymization);
+    }
+
+    Ok(())
+}
+
+fn ecdsa_key_id(config: &CanisterConfig) -> Result<EcdsaKeyId, String> {
+    Ok(EcdsaKeyId {
+        curve: config.signing_curve.ecdsa_curve()?,
+        name: config.ecdsa_key_name.clone(),
+    })
+}
+
+/// Derivation path for a patient's diagnosis signatures under the current config.
+fn patient_derivation_path(anonymized_id: &str, config: &CanisterConfig) -> Vec<Vec<u8>> {
+    if config.per_patient_key_derivation {
+        vec![anonymized_id.as_bytes().to_vec()]
+    } else {
+        vec![]
+    }
+}
+
+async fn derive_public_key(derivation_path: Vec<Vec<u8>>, config: &CanisterConfig) -> Result<Vec<u8>, String> {
+    let public_key_result = ecdsa_public_key(&EcdsaPublicKeyArgs {
         canister_id: None,
-        derivation_path: vec![],
-        key_id: key_id.clone(),
+        derivation_path,
+        key_id: ecdsa_key_id(config)?,
     })
     .await
     .map_err(|e| format!("Failed to get public key: {:?}", e))?;
 
+    Ok(public_key_result.public_key)
+}
+
+async fn create_cryptographic_signature(data: &[u8], derivation_path: Vec<Vec<u8>>) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let config = config();
+
+    // Get public key
+    let public_key = derive_public_key(derivation_path.clone(), &config).await?;
+
     // Create signature
-    let message_hash = Sha256::digest(data.as_bytes()).to_vec();
-    let signature_result = sign_with_ecdsa(SignWithEcdsaArgument {
+    let message_hash = Sha256::digest(data).to_vec();
+    let signature_result = sign_with_ecdsa(&SignWithEcdsaArgs {
         message_hash,
-        derivation_path: vec![],
-        key_id,
+        derivation_path,
+        key_id: ecdsa_key_id(&config)?,
     })
     .await
     .map_err(|e| format!("Failed to create signature: {:?}", e))?;
 
-    Ok((signature_result.0.signature, public_key_result.0.public_key))
+    Ok((signature_result.signature, public_key))
 }
 
-fn add_audit_entry(diagnosis_id: u64, action: String, details: String) {
-    let audit_id = NEXT_AUDIT_ID.with(|id| {
-        let current = *id.borrow();
-        *id.borrow_mut() = current + 1;
-        current
-    });
-
-    let audit_entry = MedicalAuditEntry {
-        id: audit_id,
-        diagnosis_id,
-        action,
-        timestamp: time(),
-        principal_id: msg_caller(),
-        details,
-        compliance_flags: vec!["FDA_AUDIT".to_string(), "HIPAA_LOG".to_string()],
+/// Version 1: `diagnosis|confidence|timestamp|anonymized_id`.
+/// Version 2 and later prefix the version: `v2|diagnosis|confidence|timestamp|anonymized_id`.
+fn diagnosis_signing_payload(
+    schema_version: u16,
+    diagnosis: &str,
+    confidence_score: f32,
+    confidence_bps: Option<u16>,
+    timestamp: u64,
+    anonymized_id: &str,
+) -> String {
+    let confidence = match confidence_bps {
+        Some(bps) => format!("{}bps", bps),
+        None => confidence_score.to_string(),
     };
+    match schema_version {
+        LEGACY_PAYLOAD_SCHEMA_VERSION => format!("{}|{}|{}|{}", diagnosis, confidence, timestamp, anonymized_id),
+        version => format!("v{}|{}|{}|{}|{}", version, diagnosis, confidence, timestamp, anonymized_id),
+    }
+}
 
-    AUDIT_TRAIL.with(|trail| {
-        trail.borrow_mut().insert(audit_id, audit_entry);
-    });
+/// `diagnosis_id (u64 BE) || verdict (1 byte) || timestamp (u64 BE)`, signed by `attest_verification`.
+fn attestation_payload(diagnosis_id: u64, verified: bool, timestamp: u64) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(17);
+    payload.extend_from_slice(&diagnosis_id.to_be_bytes());
+    payload.push(verified as u8);
+    payload.extend_from_slice(&timestamp.to_be_bytes());
+    payload
 }
 
-// Canister Interface
-#[update]
-async fn analyze_medical_image(
-    image_data: Vec<u8>,
-    patient_metadata: PatientMetadata,
-) -> Result<MedicalDiagnosisResult, String> {
-    let start_time = time();
-    
-    // Validate image
-    let _metrics = validate_medical_image(&image_data)?;
-    
-    // Perform AI analysis
-    let (diagnosis, confidence_score, medical_findings) = analyze_chest_xray(&image_data);
-    
-    // Create diagnosis data for signature
-    let diagnosis_data = format!(
-        "{}|{}|{}|{}",
-        diagnosis,
-        confidence_score,
-        start_time,
-        patient_metadata.anonymized_id
-    );
-    
-    // Generate cryptographic signature
-    let (signature, public_key) = create_cryptographic_signature(&diagnosis_data)
-        .await
-        .map_err(|e| format!("Signature generation failed: {}", e))?;
-    
-    let diagnosis_id = NEXT_DIAGNOSIS_ID.with(|id| {
-        let current = *id.borrow();
-        *id.borrow_mut() = current + 1;
-        current
-    });
-    
-    let result = MedicalDiagnosisResult {
-        id: diagnosis_id,
-        diagnosis: diagnosis.clone(),
-        confidence_score,
-        medical_findings,
-        timestamp: start_time,
-        signature,
-        public_key,
-        fda_compliant: true,
-        hipaa_compliant: true,
-        model_version: "MedicalAI-v2.1.0".to_string(),
-        patient_metadata,
-    };
-    
-    // Store diagnosis
-    DIAGNOSES.with(|diagnoses| {
-        diagnoses.borrow_mut().insert(diagnosis_id, result.clone());
-    });
-    
-    // Add audit entry
-    add_audit_entry(
-        diagnosis_id,
-        "DIAGNOSIS_CREATED".to_string(),
-        format!("Medical image analyzed: {}", diagnosis),
-    );
-    
-    Ok(result)
+/// Candid encoding of the config followed by the snapshot timestamp (u64 BE).
+fn config_snapshot_payload(config: &CanisterConfig, timestamp: u64) -> Vec<u8> {
+    let mut payload = candid::encode_one(config).unwrap();
+    payload.extend_from_slice(&timestamp.to_be_bytes());
+    payload
 }
 
-#[query]
-fn get_diagnosis(diagnosis_id: u64) -> Option<MedicalDiagnosisResult> {
-    DIAGNOSES.with(|diagnoses| {
-        diagnoses.borrow().get(&diagnosis_id)
-    })
+fn confidence_to_bps(confidence: f32) -> u16 {
+    (confidence.clamp(0.0, 1.0) * 10_000.0).round() as u16
 }
 
-#[query]
-fn get_all_diagnoses() -> Vec<MedicalDiagnosisResult> {
-    DIAGNOSES.with(|diagnoses| {
-        diagnoses.borrow().iter().map(|(_, diagnosis)| diagnosis).collect()
-    })
+fn bps_to_confidence(bps: u16) -> f32 {
+    bps as f32 / 10_000.0
 }
 
-#[query]
-fn get_medical_audit_trail() -> Vec<MedicalAuditEntry> {
-    AUDIT_TRAIL.with(|trail| {
-        trail.borrow().iter().map(|(_, entry)| entry).collect()
-    })
+/// Signs a diagnosis payload, or returns empty signature material when signing is disabled.
+async fn sign_diagnosis_payload(
+    payload: &str,
+    derivation_path: Vec<Vec<u8>>,
+    config: &CanisterConfig,
+) -> Result<(Vec<u8>, Vec<u8>), String> {
+    if !config.signing_enabled {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    create_cryptographic_signature(payload.as_bytes(), derivation_path).await
 }
 
-#[query]
-fn get_audit_trail_for_diagnosis(diagnosis_id: u64) -> Vec<MedicalAuditEntry> {
-    AUDIT_TRAIL.with(|trail| {
-        trail.borrow()
-            .iter()
-            .filter_map(|(_, entry)| {
-                if entry.diagnosis_id == diagnosis_id {
-                    Some(entry)
-                } else {
-                    None
+fn review_signing_payload(diagnosis_id: u64, decision: ReviewDecision, reviewer: Principal, timestamp: u64) -> String {
+    format!("{}|{:?}|{}|{}", diagnosis_id, decision, reviewer, timestamp)
+}
+
+fn audit_signing_payload(entry: &MedicalAuditEntry) -> String {
+    format!(
+        "{}|{}|{}|{}|{}|{}|{}",
+        entry.id,
+        entry.diagnosis_id,
+        entry.action,
+        entry.timestamp,
+        entry.principal_id,
+        entry.details,
+        entry.compliance_flags.join(",")
+    )
+}
+
+/// SHA-256 over an entry's signing payload and its own link, so each hash commits to the whole chain.
+fn audit_chain_hash(entry: &MedicalAuditEntry) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(audit_signing_payload(entry).as_bytes());
+    hasher.update(entry.previous_hash.as_deref().unwrap_or_default());
+    hasher.finalize().to_vec()
+}
+
+async fn sign_audit_entry(audit_id: u64) {
+    let Some(entry) = AUDIT_TRAIL.with(|trail| trail.borrow().get(&audit_id)) else {
+        return;
+    };
+
+    // Runs as a background task, so failures are logged rather than trapping
+    let payload = audit_signing_payload(&entry);
+    match create_cryptographic_signature(payload.as_bytes(), vec![]).await {
+        Ok((signature, public_key)) => {
+            AUDIT_TRAIL.with(|trail| {
+                let mut trail = trail.borrow_mut();
+                // An amendment during the signing call leaves the re-signing to its own task
+                if let Some(mut entry) = trail.get(&audit_id).filter(|entry| audit_signing_payload(entry) == payload) {
+                    entry.entry_signature = Some(signature);
+                    entry.entry_public_key = Some(public_key);
+                    trail.insert(audit_id, entry);
                 }
-            })
-            .collect()
-    })
+            });
+        }
+        Err(e) => ic_cdk::println!("Failed to sign audit entry {}: {}", audit_id, e),
+    }
 }
 
-#[query]
-fn verify_diagnosis_signature(diagnosis_id: u64) -> Result<bool, String> {
-    let diagnosis = DIAGNOSES.with(|diagnoses| {
-        diagnoses.borrow().get(&diagnosis_id)
-    }).ok_or("Diagnosis not found")?;
-    
-    // In a real implementation, we would verify the ECDSA signature
-    // For demo purposes, we'll simulate verification
-    let diagnosis_data = format!(
-        "{}|{}|{}|{}",
-        diagnosis.diagnosis,
+fn verify_ecdsa_signature(data: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    let Ok(verifying_key) = VerifyingKey::from_sec1_bytes(public_key) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(signature) else {
+        return false;
+    };
+    // The IC does not guarantee low-S signatures, which k256 requires
+    let signature = signature.normalize_s().unwrap_or(signature);
+    let message_hash = Sha256::digest(data);
+
+    verifying_key.verify_prehash(&message_hash, &signature).is_ok()
+}
+
+fn config() -> CanisterConfig {
+    CONFIG.with(|config| config.borrow().get().clone())
+}
+
+fn apply_output_filters(mut diagnosis: MedicalDiagnosisResult, config: &CanisterConfig) -> MedicalDiagnosisResult {
+    if let Some(floor) = config.output_finding_confidence_floor {
+        diagnosis.medical_findings.retain(|finding| finding.confidence >= floor);
+    }
+    diagnosis
+}
+
+/// The exact bytes whose SHA-256 digest is signed for a stored diagnosis.
+fn canonical_payload(diagnosis: &MedicalDiagnosisResult) -> Vec<u8> {
+    diagnosis_signing_payload(
+        diagnosis.payload_schema_version(),
+        &diagnosis.diagnosis,
         diagnosis.confidence_score,
+        diagnosis.confidence_bps,
         diagnosis.timestamp,
-        diagnosis.patient_metadata.anonymized_id
-    );
-    
-    // Simulate signature verification (always returns true for demo)
-    Ok(diagnosis_data.len() > 0 && !diagnosis.signature.is_empty())
+        &diagnosis.patient_metadata.anonymized_id,
+    )
+    .into_bytes()
 }
 
-#[update]
-fn get_fda_compliance_report(diagnosis_id: u64) -> Result<ComplianceReport, String> {
-    let diagnosis = DIAGNOSES.with(|diagnoses| {
-        diagnoses.borrow().get(&diagnosis_id)
-    }).ok_or("Diagnosis not found")?;
-    
-    // Add audit entry for compliance report generation
-    add_audit_entry(
-        diagnosis_id,
-        "COMPLIANCE_REPORT_GENERATED".to_string(),
-        "FDA compliance report requested".to_string(),
-    );
-    
-    let report = ComplianceReport {
-        diagnosis_id,
-        fda_status: if diagnosis.fda_compliant {
-            "COMPLIANT - FDA 21 CFR Part 820".to_string()
-        } else {
-            "NON_COMPLIANT".to_string()
-        },
-        hipaa_status: if diagnosis.hipaa_compliant {
-            "COMPLIANT - HIPAA Privacy Rule".to_string()
-        } else {
-            "NON_COMPLIANT".to_string()
-        },
-        audit_trail_complete: true,
-        signature_verified: true,
-        regulatory_notes: vec![
-            "Medical AI system meets FDA software as medical device requirements".to_string(),
-            "Patient data anonymized per HIPAA standards".to_string(),
-            "Cryptographic signatures ensure data integrity".to_string(),
-        ],
-        certification_level: "Class II Medical Device Software".to_string(),
-        generated_timestamp: time(),
-    };
-    
-    Ok(report)
+/// Only secp256k1 signatures can be checked; records on any other curve do not verify.
+fn verify_stored_signature(diagnosis: &MedicalDiagnosisResult) -> bool {
+    diagnosis.signing_curve() == SigningCurve::Secp256k1 && verify_ecdsa_signature(&canonical_payload(diagnosis), &diagnosis.signature, &diagnosis.public_key)
 }
 
-#[query]
-fn get_system_health() -> String {
-    let diagnosis_count = DIAGNOSES.with(|diagnoses| diagnoses.borrow().len());
-    let audit_count = AUDIT_TRAIL.with(|trail| trail.borrow().len());
-    
-    format!(
-        "Medical AI System Status: HEALTHY | Diagnoses: {} | Audit Entries: {} | Model: MedicalAI-v2.1.0",
-        diagnosis_count, audit_count
-    )
+fn is_admin(principal: &Principal) -> bool {
+    ADMINS.with(|admins| admins.borrow().contains_key(principal))
 }
 
-// Canister lifecycle
-#[init]
-fn init() {
-    ic_cdk::println!("Medical AI Backend Canister Initialized");
+fn is_provider(principal: &Principal) -> bool {
+    PROVIDERS.with(|providers| providers.borrow().contains_key(principal))
 }
 
-#[pre_upgrade]
-fn pre_upgrade() {
-    ic_cdk::println!("Medical AI Backend: Pre-upgrade hook called");
+fn require_authenticated() -> Result<Principal, MedicalError> {
+    let caller = msg_caller();
+    if caller == Principal::anonymous() {
+        Err(MedicalError::AnonymousCaller)
+    } else {
+        Ok(caller)
+    }
 }
 
-#[post_upgrade]
-fn post_upgrade() {
-    ic_cdk::println!("Medical AI Backend: Post-upgrade hook called");
+fn require_admin() -> Result<Principal, MedicalError> {
+    let caller = msg_caller();
+    if is_admin(&caller) {
+        Ok(caller)
+    } else {
+        Err(MedicalError::Unauthorized)
+    }
 }
 
-// Export Candid interface
-ic_cdk::export_candid!();
+fn require_admin_or_provider() -> Result<Principal, MedicalError> {
+    let caller = msg_caller();
+    if is_admin(&caller) || is_provider(&caller) {
+        Ok(caller)
+    } else {
+        Err(MedicalError::Unauthorized)
+    }
+}
+
+/// Picks the next on-call reviewer round-robin, skipping principals no longer registered as providers.
+fn next_on_call_reviewer(config: &CanisterConfig) -> Option<Principal> {
+    let on_call: Vec<Principal> = config.on_call_reviewers.iter()
+        .filter(|reviewer| is_provider(reviewer))
+        .copied()
+        .collect();
+    if on_call.is_empty() {
+        return None;
+    }
+
+    NEXT_ON_CALL_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        let reviewer = on_call[*index % on_call.len()];
+        *index = index.wrapping_add(1);
+        Some(reviewer)
+    })
+}
+
+fn assign_reviewer(diagnosis: &mut MedicalDiagnosisResult, config: &CanisterConfig) {
+    diagnosis.assigned_reviewer = next_on_call_reviewer(config);
+    if let Some(reviewer) = diagnosis.assigned_reviewer {
+        add_audit_entry(
+            diagnosis.id,
+            "REVIEW_ASSIGNED".to_string(),
+            format!("Review assigned to on-call reviewer {}", reviewer),
+        );
+    }
+}
+
+/// Notifies each configured channel once per severity present in the findings.
+fn dispatch_escalations(diagnosis: &MedicalDiagnosisResult, config: &CanisterConfig) {
+    for channel in &config.escalation_channels {
+        let findings: Vec<String> = diagnosis.medical_findings.iter()
+            .filter(|finding| Severity::parse(&finding.severity) == Some(channel.severity))
+            .map(|finding| format!("{} ({})", finding.finding, finding.location))
+            .collect();
+        if findings.is_empty() {
+            continue;
+        }
+
+        let notice = EscalationNotice {
+            diagnosis_id: diagnosis.id,
+            severity: channel.severity,
+            findings,
+        };
+        let (action, details) = match Call::bounded_wait(channel.target, "notify_escalation").with_arg(notice).oneway() {
+            Ok(()) => (
+                "ESCALATION_DISPATCHED",
+                format!("{:?} findings sent to channel {}", channel.severity, channel.target),
+            ),
+            Err(e) => (
+                "ESCALATION_FAILED",
+                format!("{:?} findings not sent to channel {}: {:?}", channel.severity, channel.target, e),
+            ),
+        };
+        add_audit_entry(diagnosis.id, action.to_string(), details);
+    }
+}
+
+fn record_error(endpoint: &str, error: &MedicalError) {
+    RECENT_ERRORS.with(|errors| {
+        let mut errors = errors.borrow_mut();
+        if errors.len() == MAX_RECENT_ERRORS {
+            errors.pop_front();
+        }
+        errors.push_back(ErrorEvent {
+            timestamp: time(),
+            endpoint: endpoint.to_string(),
+            error: error.clone(),
+            caller: msg_caller(),
+        });
+    });
+}
+
+/// Runs an update endpoint body, recording any error it returns in the recent errors buffer.
+async fn track_errors<T>(endpoint: &str, call: impl Future<Output = Result<T, MedicalError>>) -> Result<T, MedicalError> {
+    call.await.inspect_err(|error| record_error(endpoint, error))
+}
+
+/// Synchronous counterpart of `track_errors` for endpoints that make no inter-canister calls.
+fn track_sync_errors<T>(endpoint: &str, call: impl FnOnce() -> Result<T, MedicalError>) -> Result<T, MedicalError> {
+    call().inspect_err(|error| record_error(endpoint, error))
+}
+
+fn validate_free_text(texts: &[&str]) -> Result<(), MedicalError> {
+    if texts.iter().any(|text| text.len() > MAX_FREE_TEXT_BYTES) {
+        return Err(MedicalError::TextTooLong(MAX_FREE_TEXT_BYTES as u64));
+    }
+    Ok(())
+}
+
+fn check_signing_rate_limit(principal: Principal) -> Result<(), MedicalError> {
+    let now = time();
+    SIGNING_REQUESTS.with(|requests| {
+        let mut requests = requests.borrow_mut();
+        let recent = requests.entry(principal).or_default();
+        recent.retain(|&t| now.saturating_sub(t) < SIGNING_RATE_LIMIT_WINDOW_NS);
+
+        if recent.len() >= MAX_SIGNING_REQUESTS_PER_WINDOW {
+            return Err(MedicalError::RateLimited);
+        }
+        recent.push(now);
+        Ok(())
+    })
+}
+
+/// Allocates past every ID already in use, so canisters upgraded from before the counter
+/// was persisted resume after their highest stored, archived or deleted diagnosis.
+fn next_diagnosis_id() -> u64 {
+    let after = |last: Option<u64>| last.map_or(1, |id| id + 1);
+    let in_use = [
+        DIAGNOSES.with(|diagnoses| after(diagnoses.borrow().last_key_value().map(|(id, _)| id))),
+        ARCHIVED_DIAGNOSES.with(|archive| after(archive.borrow().last_key_value().map(|(id, _)| id))),
+        DELETED_DIAGNOSES.with(|deleted| after(deleted.borrow().last_key_value().map(|(id, _)| id))),
+    ];
+
+    NEXT_DIAGNOSIS_ID.with(|next| {
+        let mut next = next.borrow_mut();
+        let current = in_use.into_iter().fold(*next.get(), u64::max);
+        next.set(current + 1).expect("Failed to persist diagnosis ID counter");
+        current
+    })
+}
+
+fn load_diagnosis(diagnosis_id: u64) -> Result<MedicalDiagnosisResult, MedicalError> {
+    DIAGNOSES.with(|diagnoses| diagnoses.borrow().get(&diagnosis_id))
+        .ok_or(MedicalError::DiagnosisNotFound)
+}
+
+/// Looks up a diagnosis in the hot map, then the archive, for read-only verification.
+fn load_verifiable_diagnosis(diagnosis_id: u64) -> Result<MedicalDiagnosisResult, MedicalError> {
+    load_diagnosis(diagnosis_id).or_else(|_| {
+        ARCHIVED_DIAGNOSES.with(|archive| archive.borrow().get(&diagnosis_id))
+            .ok_or(MedicalError::DiagnosisNotFound)
+    })
+}
+
+/// Applies the oversized findings policy so the record fits in stable storage.
+/// Returns the number of findings dropped.
+fn fit_findings(diagnosis: &mut MedicalDiagnosisResult, config: &CanisterConfig) -> Result<usize, MedicalError> {
+    let fits = |diagnosis: &MedicalDiagnosisResult| diagnosis.to_bytes().len() <= MAX_DIAGNOSIS_BYTES as usize;
+    if fits(diagnosis) {
+        return Ok(0);
+    }
+    if config.oversized_findings_policy == OversizedFindingsPolicy::Reject {
+        return Err(MedicalError::ResultTooLarge);
+    }
+
+    let original_count = diagnosis.medical_findings.len();
+    diagnosis.medical_findings.sort_by(|a, b| {
+        Severity::priority(&b.severity)
+            .cmp(&Severity::priority(&a.severity))
+            .then(b.confidence.total_cmp(&a.confidence))
+    });
+    while !fits(diagnosis) {
+        if diagnosis.medical_findings.pop().is_none() {
+            return Err(MedicalError::ResultTooLarge);
+        }
+    }
+
+    Ok(original_count - diagnosis.medical_findings.len())
+}
+
+fn store_diagnosis(diagnosis: &MedicalDiagnosisResult) {
+    DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow_mut().insert(diagnosis.id, diagnosis.clone());
+    });
+}
+
+/// Lists the compliance criteria a stored diagnosis currently fails, empty when compliant.
+fn compliance_failures(diagnosis: &MedicalDiagnosisResult) -> Vec<String> {
+    let mut failures = Vec::new();
+
+    if !is_active_model(&diagnosis.model_version) {
+        failures.push(format!("INACTIVE_MODEL: {} is not a registered active model", diagnosis.model_version));
+    }
+    let consented = CONSENTS.with(|consents| {
+        consents.borrow()
+            .get(&diagnosis.patient_metadata.anonymized_id)
+            .is_some_and(|consent| consent.scopes.contains(&ConsentScope::Diagnosis))
+    });
+    if !consented {
+        failures.push("MISSING_CONSENT: no diagnosis consent on record for patient".to_string());
+    }
+    if diagnosis.signature.is_empty() {
+        failures.push("UNSIGNED: diagnosis carries no cryptographic signature".to_string());
+    }
+    if diagnosis.patient_metadata.anonymized_id.trim().is_empty() {
+        failures.push("DEIDENTIFICATION_FAILED: anonymized_id is empty".to_string());
+    }
+    if !diagnosis.fda_compliant {
+        failures.push("FDA_FLAG: diagnosis is marked FDA non-compliant".to_string());
+    }
+    if !diagnosis.hipaa_compliant {
+        failures.push("HIPAA_FLAG: diagnosis is marked HIPAA non-compliant".to_string());
+    }
+
+    failures
+}
+
+fn build_compliance_report(diagnosis: &MedicalDiagnosisResult) -> ComplianceReport {
+    let config = config();
+    let signature_verified = verify_stored_signature(diagnosis);
+    let mut regulatory_notes = vec![
+        "Medical AI system meets FDA software as medical device requirements".to_string(),
+        "Patient data anonymized per HIPAA standards".to_string(),
+    ];
+    if diagnosis.signature.is_empty() {
+        regulatory_notes.push(if config.signing_enabled {
+            "Diagnosis is unsigned; it was produced while signing was disabled".to_string()
+        } else {
+            "Diagnosis is unsigned; cryptographic signing is currently disabled".to_string()
+        });
+    } else if signature_verified {
+        regulatory_notes.push("Cryptographic signatures ensure data integrity".to_string());
+    } else {
+        regulatory_notes.push("Stored signature does not verify against the canonical payload".to_string());
+    }
+
+    let fda_compliant = diagnosis.fda_compliant
+        && (signature_verified || !config.require_signatures_for_compliance);
+
+    ComplianceReport {
+        diagnosis_id: diagnosis.id,
+        fda_status: if fda_compliant {
+            "COMPLIANT - FDA 21 CFR Part 820".to_string()
+        } else if diagnosis.fda_compliant {
+            "NON_COMPLIANT - verified signature required".to_string()
+        } else {
+            "NON_COMPLIANT".to_string()
+        },
+        hipaa_status: if diagnosis.hipaa_compliant {
+            "COMPLIANT - HIPAA Privacy Rule".to_string()
+        } else {
+            "NON_COMPLIANT".to_string()
+        },
+        audit_trail_complete: true,
+        signature_verified,
+        regulatory_notes,
+        certification_level: "Class II Medical Device Software".to_string(),
+        generated_timestamp: time(),
+    }
+}
+
+fn validate_consent(record: &ConsentRecord) -> Result<(), MedicalError> {
+    if record.anonymized_id.trim().is_empty() {
+        return Err(MedicalError::InvalidConsent("anonymized_id is empty".to_string()));
+    }
+    if record.consent_hash.len() != 64 || !record.consent_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(MedicalError::InvalidConsent("consent_hash must be a hex-encoded SHA-256 digest".to_string()));
+    }
+    if record.scopes.is_empty() {
+        return Err(MedicalError::InvalidConsent("at least one consent scope is required".to_string()));
+    }
+    Ok(())
+}
+
+fn store_consent(record: ConsentRecord) -> Result<(), MedicalError> {
+    validate_consent(&record)?;
+
+    // Consent is patient-scoped rather than diagnosis-scoped, so it is logged against ID 0
+    add_audit_entry(
+        0,
+        "CONSENT_RECORDED".to_string(),
+        format!("Consent {} recorded for scopes {:?}", record.consent_hash, record.scopes),
+    );
+    CONSENTS.with(|consents| {
+        consents.borrow_mut().insert(record.anonymized_id.clone(), record);
+    });
+
+    Ok(())
+}
+
+/// Nearest-rank percentiles over `samples`, which must be sorted ascending.
+fn latency_percentiles(samples: &[u64]) -> LatencyPercentiles {
+    let rank = |percentile: usize| {
+        if samples.is_empty() {
+            return 0;
+        }
+        let index = (percentile * samples.len()).div_ceil(100).max(1) - 1;
+        samples[index]
+    };
+
+    LatencyPercentiles {
+        p50_ms: rank(50),
+        p90_ms: rank(90),
+        p99_ms: rank(99),
+    }
+}
+
+/// Binary Merkle root over SHA-256 leaves, duplicating the last node on odd levels.
+/// An empty set has an all-zero root.
+fn merkle_root(mut level: Vec<[u8; 32]>) -> [u8; 32] {
+    if level.is_empty() {
+        return [0; 32];
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let mut hasher = Sha256::new();
+                hasher.update(pair[0]);
+                hasher.update(pair.get(1).unwrap_or(&pair[0]));
+                hasher.finalize().into()
+            })
+            .collect();
+    }
+    level[0]
+}
+
+fn diagnosis_leaf(id: u64, diagnosis: &MedicalDiagnosisResult) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(id.to_be_bytes());
+    // Plain Candid rather than the stored form, so toggling compression does not change it
+    hasher.update(candid::encode_one(diagnosis).unwrap());
+    hasher.finalize().into()
+}
+
+fn record_type_stats<V: Storable>(records: impl Iterator<Item = V>) -> RecordTypeStats {
+    let (count, total_bytes) = records.fold((0u64, 0u64), |(count, total), record| {
+        (count + 1, total + record.to_bytes().len() as u64)
+    });
+
+    RecordTypeStats {
+        count,
+        total_bytes,
+        average_bytes: total_bytes.checked_div(count).unwrap_or(0),
+    }
+}
+
+fn dedup_key(anonymized_id: &str, image_data: &[u8]) -> String {
+    format!("{}:{}", anonymized_id, hex::encode(Sha256::digest(image_data)))
+}
+
+/// Returns the live diagnosis for a recently analyzed image, evicting the entry once expired.
+fn find_deduplicated_diagnosis(key: &str, config: &CanisterConfig) -> Option<MedicalDiagnosisResult> {
+    if config.dedup_ttl_ns == 0 {
+        return None;
+    }
+
+    let entry = IMAGE_DEDUP.with(|dedup| dedup.borrow().get(&key.to_string()))?;
+    if time().saturating_sub(entry.created_at) >= config.dedup_ttl_ns {
+        IMAGE_DEDUP.with(|dedup| dedup.borrow_mut().remove(&key.to_string()));
+        return None;
+    }
+
+    DIAGNOSES.with(|diagnoses| diagnoses.borrow().get(&entry.diagnosis_id))
+        .filter(|diagnosis| matches!(diagnosis.status(), DiagnosisStatus::Active | DiagnosisStatus::UnderReview))
+}
+
+/// Moves a diagnosis's dedup entries to its new anonymized ID, so resubmissions under the
+/// new ID still deduplicate and those under the old one no longer resolve to it.
+fn rekey_dedup_entries(diagnosis_id: u64, old_anonymized_id: &str, new_anonymized_id: &str) {
+    let prefix = format!("{}:", old_anonymized_id);
+    IMAGE_DEDUP.with(|dedup| {
+        let mut dedup = dedup.borrow_mut();
+        let entries: Vec<(String, DedupEntry)> = dedup
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .filter(|(_, entry)| entry.diagnosis_id == diagnosis_id)
+            .collect();
+        for (key, entry) in entries {
+            dedup.remove(&key);
+            dedup.insert(format!("{}:{}", new_anonymized_id, &key[prefix.len()..]), entry);
+        }
+    });
+}
+
+/// Scans up to `limit` dedup entries from where the previous sweep stopped and evicts the expired
+/// ones. With deduplication disabled every entry is stale. Returns the number evicted.
+fn sweep_dedup_entries(limit: u64, config: &CanisterConfig) -> u64 {
+    let now = time();
+    let cursor = DEDUP_SWEEP_CURSOR.with(|cursor| cursor.borrow_mut().take());
+    let (scanned, expired): (Vec<String>, Vec<String>) = IMAGE_DEDUP.with(|dedup| {
+        let dedup = dedup.borrow();
+        let entries: Vec<(String, DedupEntry)> = match cursor {
+            Some(cursor) => dedup.range((Bound::Excluded(cursor), Bound::Unbounded)).take(limit as usize).collect(),
+            None => dedup.iter().take(limit as usize).collect(),
+        };
+        let expired = entries.iter()
+            .filter(|(_, entry)| config.dedup_ttl_ns == 0 || now.saturating_sub(entry.created_at) >= config.dedup_ttl_ns)
+            .map(|(key, _)| key.clone())
+            .collect();
+        (entries.into_iter().map(|(key, _)| key).collect(), expired)
+    });
+
+    // A short page means the end was reached, so the next sweep wraps around
+    if scanned.len() as u64 == limit {
+        DEDUP_SWEEP_CURSOR.with(|cursor| *cursor.borrow_mut() = scanned.last().cloned());
+    }
+
+    IMAGE_DEDUP.with(|dedup| {
+        let mut dedup = dedup.borrow_mut();
+        for key in &expired {
+            dedup.remove(key);
+        }
+    });
+    expired.len() as u64
+}
+
+fn take_upload_session(upload_id: u64) -> Result<UploadSession, MedicalError> {
+    let caller = require_authenticated()?;
+    UPLOAD_SESSIONS.with(|sessions| {
+        let mut sessions = sessions.borrow_mut();
+        match sessions.get(&upload_id) {
+            Some(session) if session.owner == caller => Ok(sessions.remove(&upload_id).unwrap()),
+            _ => Err(MedicalError::UploadNotFound),
+        }
+    })
+}
+
+fn add_audit_entry(diagnosis_id: u64, action: String, details: String) {
+    let config = config();
+    if let Some(cap) = config.max_audit_entries_per_diagnosis {
+        let count = AUDIT_ENTRY_COUNTS.with(|counts| counts.borrow().get(&diagnosis_id).unwrap_or(0));
+        let since = time().saturating_sub(config.audit_coalesce_window_ns);
+
+        // Canister-level entries share ID 0 and are never coalesced
+        if diagnosis_id != 0 && count >= cap && has_recent_audit_entry(diagnosis_id, since, |entry| entry.action == action) {
+            let marker = format!("Further {} entries suppressed after reaching the cap of {}", action, cap);
+            let marked = has_recent_audit_entry(diagnosis_id, since, |entry| {
+                entry.action == "AUDIT_SUPPRESSED" && entry.details == marker
+            });
+            if !marked {
+                append_audit_entry(diagnosis_id, "AUDIT_SUPPRESSED".to_string(), marker);
+            }
+            return;
+        }
+    }
+
+    append_audit_entry(diagnosis_id, action, details);
+}
+
+/// Scans back from the newest audit entry to `since` for a match on `diagnosis_id`.
+fn has_recent_audit_entry(diagnosis_id: u64, since: u64, matches: impl Fn(&MedicalAuditEntry) -> bool) -> bool {
+    AUDIT_TRAIL.with(|trail| {
+        trail.borrow()
+            .iter()
+            .rev()
+            .map(|(_, entry)| entry)
+            .take_while(|entry| entry.timestamp >= since)
+            .any(|entry| entry.diagnosis_id == diagnosis_id && matches(&entry))
+    })
+}
+
+fn append_audit_entry(diagnosis_id: u64, action: String, details: String) {
+    let principal_id = msg_caller();
+    let mut compliance_flags = vec!["FDA_AUDIT".to_string(), "HIPAA_LOG".to_string()];
+    // Endpoints reject anonymous callers up front; flag any entry that slips through
+    if principal_id == Principal::anonymous() {
+        compliance_flags.push("ANONYMOUS_CALLER".to_string());
+    }
+
+    // Entries are never removed, so the trail itself is the upgrade-safe ID counter
+    let audit_id = AUDIT_TRAIL.with(|trail| {
+        let mut trail = trail.borrow_mut();
+        let previous = trail.last_key_value();
+        let audit_id = previous.as_ref().map_or(1, |(id, _)| id + 1);
+        let previous_hash = previous.map(|(_, previous)| audit_chain_hash(&previous));
+        trail.insert(audit_id, MedicalAuditEntry {
+            id: audit_id,
+            diagnosis_id,
+            action,
+            timestamp: time(),
+            principal_id,
+            details,
+            compliance_flags,
+            entry_signature: None,
+            entry_public_key: None,
+            previous_hash: Some(previous_hash.unwrap_or_default()),
+        });
+        audit_id
+    });
+    AUDIT_ENTRY_COUNTS.with(|counts| {
+        let mut counts = counts.borrow_mut();
+        let count = counts.get(&diagnosis_id).unwrap_or(0);
+        counts.insert(diagnosis_id, count + 1);
+    });
+
+    let config = config();
+    if config.sign_audit_entries && config.signing_enabled {
+        spawn(sign_audit_entry(audit_id));
+    }
+}
+
+async fn run_analysis(
+    image_data: Vec<u8>,
+    mut patient_metadata: PatientMetadata,
+) -> Result<MedicalDiagnosisResult, MedicalError> {
+    let start_time = time();
+    let config = config();
+    
+    // Validate image
+    let metrics = validate_medical_image(&image_data).map_err(MedicalError::InvalidImage)?;
+    normalize_acquisition_date(&mut patient_metadata, &config)?;
+    validate_acquisition_date(&patient_metadata, &config, start_time)?;
+    validate_anonymization(&patient_metadata, &config)?;
+    validate_model_version(MODEL_VERSION, &config)?;
+
+    let image_key = dedup_key(&patient_metadata.anonymized_id, &image_data);
+    if let Some(existing) = find_deduplicated_diagnosis(&image_key, &config) {
+        add_audit_entry(
+            existing.id,
+            "DUPLICATE_IMAGE_DEDUPLICATED".to_string(),
+            "Identical image resubmitted within dedup window; returned existing diagnosis".to_string(),
+        );
+        return Ok(existing);
+    }
+    
+    // Perform AI analysis
+    let (diagnosis, raw_confidence_score, mut medical_findings) = analyze_chest_xray(&image_data);
+    let unmapped_severity = normalize_severities(&mut medical_findings, config.unmapped_severity_policy);
+    let unadjusted_confidence_score = apply_calibration(raw_confidence_score, &mut medical_findings, &config);
+    let confidence_score = apply_quality_downgrade(unadjusted_confidence_score, metrics.quality_score, &config);
+
+    let contradiction = match config.findings_consistency_policy {
+        FindingsConsistencyPolicy::Off => None,
+        _ => findings_contradiction(&diagnosis, &medical_findings),
+    };
+    if let Some(reason) = &contradiction {
+        if config.findings_consistency_policy == FindingsConsistencyPolicy::Reject {
+            return Err(MedicalError::InconsistentFindings(reason.clone()));
+        }
+    }
+    
+    // Scaled confidences are rounded to basis points, and the float form is derived from them
+    let confidence_bps = config.scaled_confidence.then(|| confidence_to_bps(confidence_score));
+    let confidence_score = confidence_bps.map_or(confidence_score, bps_to_confidence);
+
+    // Create diagnosis data for signature
+    let diagnosis_data = diagnosis_signing_payload(
+        PAYLOAD_SCHEMA_VERSION,
+        &diagnosis,
+        confidence_score,
+        confidence_bps,
+        start_time,
+        &patient_metadata.anonymized_id,
+    );
+    
+    // Generate cryptographic signature
+    let derivation_path = patient_derivation_path(&patient_metadata.anonymized_id, &config);
+    let (signature, public_key) = sign_diagnosis_payload(&diagnosis_data, derivation_path, &config)
+        .await
+        .map_err(|e| MedicalError::SignatureFailed(format!("Signature generation failed: {}", e)))?;
+    
+    let diagnosis_id = next_diagnosis_id();
+    
+    let needs_review = contradiction.is_some() || unmapped_severity;
+    let (icd10_code, snomed_code) = diagnosis_codes(&diagnosis).unzip();
+    let mut result = MedicalDiagnosisResult {
+        id: diagnosis_id,
+        diagnosis: diagnosis.clone(),
+        confidence_score,
+        raw_confidence_score: Some(raw_confidence_score),
+        unadjusted_confidence_score: Some(unadjusted_confidence_score),
+        medical_findings,
+        timestamp: start_time,
+        signature,
+        public_key,
+        fda_compliant: true,
+        hipaa_compliant: true,
+        model_version: MODEL_VERSION.to_string(),
+        patient_metadata,
+        status: Some(if needs_review {
+            DiagnosisStatus::UnderReview
+        } else {
+            DiagnosisStatus::Active
+        }),
+        review: None,
+        amends: None,
+        source_id: None,
+        assigned_reviewer: None,
+        confidence_bps,
+        icd10_code,
+        snomed_code,
+        submitted_by: Some(msg_caller()),
+        metrics: Some(metrics),
+        payload_schema_version: Some(PAYLOAD_SCHEMA_VERSION),
+        signing_curve: Some(config.signing_curve),
+    };
+    let truncated_findings = fit_findings(&mut result, &config)?;
+    
+    // Store diagnosis
+    DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow_mut().insert(diagnosis_id, result.clone());
+    });
+    if config.dedup_ttl_ns > 0 {
+        IMAGE_DEDUP.with(|dedup| {
+            dedup.borrow_mut().insert(image_key, DedupEntry {
+                diagnosis_id,
+                created_at: start_time,
+            })
+        });
+        sweep_dedup_entries(DEDUP_SWEEP_BATCH_SIZE, &config);
+    }
+    
+    // Add audit entry
+    add_audit_entry(
+        diagnosis_id,
+        "DIAGNOSIS_CREATED".to_string(),
+        format!("Medical image analyzed: {}", diagnosis),
+    );
+
+    if let Some(reason) = contradiction {
+        add_audit_entry(
+            diagnosis_id,
+            "INCONSISTENT_FINDINGS_FLAGGED".to_string(),
+            reason,
+        );
+    }
+
+    if unmapped_severity {
+        UNMAPPED_SEVERITIES.with(|unmapped| unmapped.borrow_mut().insert(diagnosis_id, ()));
+        add_audit_entry(
+            diagnosis_id,
+            "UNMAPPED_SEVERITY_FLAGGED".to_string(),
+            "Finding severity could not be mapped to a known level".to_string(),
+        );
+    }
+
+    if truncated_findings > 0 {
+        add_audit_entry(
+            diagnosis_id,
+            "FINDINGS_TRUNCATED".to_string(),
+            format!("Dropped {} lowest-priority findings to fit storage limits", truncated_findings),
+        );
+    }
+
+    if needs_review {
+        assign_reviewer(&mut result, &config);
+        store_diagnosis(&result);
+    }
+
+    dispatch_escalations(&result, &config);
+    
+    Ok(result)
+}
+
+// Canister Interface
+#[update]
+async fn analyze_medical_image(
+    image_data: Vec<u8>,
+    patient_metadata: PatientMetadata,
+) -> Result<MedicalDiagnosisResult, MedicalError> {
+    track_errors("analyze_medical_image", async {
+        require_authenticated()?;
+        run_analysis(image_data, patient_metadata).await
+    })
+    .await
+}
+
+#[update]
+fn begin_upload() -> Result<u64, MedicalError> {
+    track_sync_errors("begin_upload", || {
+        let owner = require_authenticated()?;
+        let max_sessions = config().max_upload_sessions_per_principal as usize;
+
+        UPLOAD_SESSIONS.with(|sessions| {
+            let mut sessions = sessions.borrow_mut();
+            if sessions.values().filter(|session| session.owner == owner).count() >= max_sessions {
+                return Err(MedicalError::TooManyUploads);
+            }
+
+            let upload_id = NEXT_UPLOAD_ID.with(|id| {
+                let current = *id.borrow();
+                *id.borrow_mut() = current + 1;
+                current
+            });
+            sessions.insert(upload_id, UploadSession {
+                owner,
+                data: Vec::new(),
+            });
+            Ok(upload_id)
+        })
+    })
+}
+
+#[update]
+fn upload_chunk(upload_id: u64, chunk: Vec<u8>) -> Result<(), MedicalError> {
+    track_sync_errors("upload_chunk", || {
+        let caller = require_authenticated()?;
+
+        UPLOAD_SESSIONS.with(|sessions| {
+            let mut sessions = sessions.borrow_mut();
+            let session = sessions
+                .get_mut(&upload_id)
+                .filter(|session| session.owner == caller)
+                .ok_or(MedicalError::UploadNotFound)?;
+
+            if session.data.len() + chunk.len() > MAX_IMAGE_BYTES {
+                return Err(MedicalError::InvalidImage("Image file too large - maximum 50MB allowed".to_string()));
+            }
+            session.data.extend_from_slice(&chunk);
+            Ok(())
+        })
+    })
+}
+
+#[update]
+async fn finalize_upload(
+    upload_id: u64,
+    patient_metadata: PatientMetadata,
+) -> Result<MedicalDiagnosisResult, MedicalError> {
+    track_errors("finalize_upload", async {
+        let session = take_upload_session(upload_id)?;
+        run_analysis(session.data, patient_metadata).await
+    })
+    .await
+}
+
+#[update]
+fn cancel_upload(upload_id: u64) -> Result<(), MedicalError> {
+    track_sync_errors("cancel_upload", || take_upload_session(upload_id).map(|_| ()))
+}
+
+#[query]
+fn get_diagnosis(diagnosis_id: u64, include_all_findings: Option<bool>) -> Option<MedicalDiagnosisResult> {
+    let diagnosis = DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow().get(&diagnosis_id)
+    })?;
+
+    if include_all_findings.unwrap_or(false) {
+        Some(diagnosis)
+    } else {
+        Some(apply_output_filters(diagnosis, &config()))
+    }
+}
+
+#[query]
+fn get_all_diagnoses() -> Vec<MedicalDiagnosisResult> {
+    let config = config();
+    DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow().iter().map(|(_, diagnosis)| apply_output_filters(diagnosis, &config)).collect()
+    })
+}
+
+#[query]
+fn get_medical_audit_trail() -> Vec<MedicalAuditEntry> {
+    AUDIT_TRAIL.with(|trail| {
+        trail.borrow().iter().map(|(_, entry)| entry).collect()
+    })
+}
+
+#[query]
+fn get_audit_trail_for_diagnosis(diagnosis_id: u64) -> Vec<MedicalAuditEntry> {
+    AUDIT_TRAIL.with(|trail| {
+        trail.borrow()
+            .iter()
+            .filter_map(|(_, entry)| {
+                if entry.diagnosis_id == diagnosis_id {
+                    Some(entry)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    })
+}
+
+/// Audit entry IDs whose diagnosis never existed. Entries for deleted diagnoses and
+/// canister-level entries (diagnosis ID 0) are not orphans.
+#[query]
+fn find_orphan_audit_entries() -> Vec<u64> {
+    AUDIT_TRAIL.with(|trail| {
+        trail.borrow()
+            .iter()
+            .filter(|(_, entry)| {
+                entry.diagnosis_id != 0
+                    && !DIAGNOSES.with(|diagnoses| diagnoses.borrow().contains_key(&entry.diagnosis_id))
+                    && !ARCHIVED_DIAGNOSES.with(|archive| archive.borrow().contains_key(&entry.diagnosis_id))
+                    && !is_deleted(entry.diagnosis_id)
+            })
+            .map(|(id, _)| id)
+            .collect()
+    })
+}
+
+/// Corrects the details of the newest audit entry while it is still inside the grace
+/// period. Once a later entry links it, or the period lapses, the entry is immutable.
+#[update]
+fn amend_audit_entry(audit_id: u64, details: String) -> Result<(), MedicalError> {
+    track_sync_errors("amend_audit_entry", || {
+        let admin = require_admin()?;
+        let config = config();
+
+        AUDIT_TRAIL.with(|trail| {
+            let mut trail = trail.borrow_mut();
+            let mut entry = trail.get(&audit_id).ok_or(MedicalError::AuditEntryNotFound)?;
+
+            let is_latest = trail.last_key_value().is_some_and(|(id, _)| id == audit_id);
+            let within_grace = time() <= entry.timestamp.saturating_add(config.audit_amendment_grace_ns);
+            if !is_latest || !within_grace || config.audit_amendment_grace_ns == 0 {
+                return Err(MedicalError::AuditEntryImmutable);
+            }
+
+            entry.details = details;
+            entry.compliance_flags.push(format!("AMENDED_BY:{}", admin));
+            entry.entry_signature = None;
+            entry.entry_public_key = None;
+            trail.insert(audit_id, entry);
+            Ok(())
+        })?;
+
+        if config.sign_audit_entries && config.signing_enabled {
+            spawn(sign_audit_entry(audit_id));
+        }
+
+        Ok(())
+    })
+}
+
+/// The ID of the first audit entry whose link does not match its predecessor, if any.
+#[query]
+fn verify_audit_chain() -> Option<u64> {
+    AUDIT_TRAIL.with(|trail| {
+        let mut previous_hash = Vec::new();
+        let mut chained = false;
+        for (id, entry) in trail.borrow().iter() {
+            match &entry.previous_hash {
+                // Entries written before the chain existed are unlinked, but only ahead of it
+                None if !chained => {}
+                Some(link) if *link == previous_hash => chained = true,
+                _ => return Some(id),
+            }
+            previous_hash = audit_chain_hash(&entry);
+        }
+        None
+    })
+}
+
+#[query]
+fn verify_audit_entry(audit_id: u64) -> Result<bool, MedicalError> {
+    let entry = AUDIT_TRAIL.with(|trail| trail.borrow().get(&audit_id))
+        .ok_or(MedicalError::AuditEntryNotFound)?;
+
+    Ok(verify_ecdsa_signature(
+        audit_signing_payload(&entry).as_bytes(),
+        entry.entry_signature.as_deref().unwrap_or_default(),
+        entry.entry_public_key.as_deref().unwrap_or_default(),
+    ))
+}
+
+#[query]
+fn verify_diagnosis_signature(diagnosis_id: u64) -> Result<bool, String> {
+    let diagnosis = load_verifiable_diagnosis(diagnosis_id)
+        .map_err(|_| "Diagnosis not found")?;
+    
+    Ok(verify_stored_signature(&diagnosis))
+}
+
+#[query]
+fn get_canonical_payload(diagnosis_id: u64) -> Result<Vec<u8>, MedicalError> {
+    Ok(canonical_payload(&load_verifiable_diagnosis(diagnosis_id)?))
+}
+
+/// Verifies the stored signature against a public key obtained out-of-band, ignoring the
+/// key embedded in the record.
+#[query]
+fn verify_with_key(diagnosis_id: u64, public_key: Vec<u8>) -> Result<bool, MedicalError> {
+    let diagnosis = load_verifiable_diagnosis(diagnosis_id)?;
+    Ok(verify_ecdsa_signature(&canonical_payload(&diagnosis), &diagnosis.signature, &public_key))
+}
+
+/// Packages a diagnosis with everything needed to check its signature offline, as a
+/// Candid-encoded `VerificationBundle`.
+#[query]
+fn export_verification_bundle(diagnosis_id: u64) -> Result<Vec<u8>, MedicalError> {
+    let diagnosis = load_verifiable_diagnosis(diagnosis_id)?;
+    let bundle = VerificationBundle {
+        signature: diagnosis.signature.clone(),
+        public_key: diagnosis.public_key.clone(),
+        canonical_payload: canonical_payload(&diagnosis),
+        canister_id: canister_self(),
+        signature_scheme: diagnosis.signing_curve().signature_scheme().to_string(),
+        exported_at: time(),
+        diagnosis,
+    };
+
+    candid::encode_one(&bundle).map_err(|e| MedicalError::SignatureFailed(format!("Bundle encoding failed: {}", e)))
+}
+
+/// IDs of signed diagnoses whose stored public key differs from what the current key name
+/// and derivation scheme produce for the patient, i.e. candidates for re-signing.
+#[update]
+async fn audit_key_consistency() -> Result<Vec<u64>, MedicalError> {
+    track_errors("audit_key_consistency", async {
+        require_admin()?;
+        let config = config();
+
+        let signed: Vec<(u64, String, Vec<u8>)> = DIAGNOSES.with(|diagnoses| {
+            diagnoses.borrow()
+                .iter()
+                .filter(|(_, diagnosis)| diagnosis.status() != DiagnosisStatus::Deleted && !diagnosis.public_key.is_empty())
+                .map(|(id, diagnosis)| (id, diagnosis.patient_metadata.anonymized_id, diagnosis.public_key))
+                .collect()
+        });
+
+        // One management call per distinct derivation path
+        let mut derived: HashMap<Vec<Vec<u8>>, Vec<u8>> = HashMap::new();
+        let mut mismatched = Vec::new();
+        for (id, anonymized_id, public_key) in signed {
+            let path = patient_derivation_path(&anonymized_id, &config);
+            let expected = match derived.get(&path) {
+                Some(key) => key.clone(),
+                None => {
+                    let key = derive_public_key(path.clone(), &config)
+                        .await
+                        .map_err(MedicalError::ManagementCallFailed)?;
+                    derived.insert(path, key.clone());
+                    key
+                }
+            };
+            if expected != public_key {
+                mismatched.push(id);
+            }
+        }
+
+        Ok(mismatched)
+    })
+    .await
+}
+
+#[query]
+fn verify_signatures_batch(start_id: u64, count: u64) -> SignatureBatchResult {
+    let count = count.min(MAX_SIGNATURE_BATCH_SIZE);
+    let mut result = SignatureBatchResult {
+        verified: 0,
+        failed: 0,
+        failed_ids: Vec::new(),
+        next_cursor: None,
+    };
+
+    DIAGNOSES.with(|diagnoses| ARCHIVED_DIAGNOSES.with(|archive| {
+        let (diagnoses, archive) = (diagnoses.borrow(), archive.borrow());
+        // The two maps hold disjoint IDs, so merging them keeps the cursor in ID order
+        let mut hot = diagnoses.range(start_id..).peekable();
+        let mut archived = archive.range(start_id..).peekable();
+        let mut window = std::iter::from_fn(|| match (hot.peek(), archived.peek()) {
+            (Some((hot_id, _)), Some((archived_id, _))) if archived_id < hot_id => archived.next(),
+            (Some(_), _) => hot.next(),
+            (None, _) => archived.next(),
+        });
+
+        for (id, diagnosis) in window.by_ref().take(count as usize) {
+            if verify_stored_signature(&diagnosis) {
+                result.verified += 1;
+            } else {
+                result.failed += 1;
+                result.failed_ids.push(id);
+            }
+        }
+
+        result.next_cursor = window.next().map(|(id, _)| id);
+    }));
+
+    result
+}
+
+/// Lists distinct anonymized patient IDs in ascending order with their diagnosis counts.
+/// Pass the last returned ID as `start_after` to fetch the next page.
+#[query]
+fn get_patient_roster(limit: u64, start_after: Option<String>) -> Vec<(String, u64)> {
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+
+    DIAGNOSES.with(|diagnoses| {
+        for (_, diagnosis) in diagnoses.borrow().iter() {
+            if diagnosis.status() != DiagnosisStatus::Deleted {
+                *counts.entry(diagnosis.patient_metadata.anonymized_id).or_default() += 1;
+            }
+        }
+    });
+
+    counts
+        .into_iter()
+        .filter(|(anonymized_id, _)| start_after.as_ref().is_none_or(|after| anonymized_id > after))
+        .take(limit.min(MAX_ROSTER_PAGE_SIZE) as usize)
+        .collect()
+}
+
+/// A patient's non-deleted diagnoses in chronological order, with the gap between studies.
+#[query]
+fn get_patient_timeline(anonymized_id: String) -> Vec<TimelineEntry> {
+    let config = config();
+    let mut diagnoses: Vec<MedicalDiagnosisResult> = DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow()
+            .iter()
+            .map(|(_, diagnosis)| diagnosis)
+            .filter(|diagnosis| {
+                diagnosis.patient_metadata.anonymized_id == anonymized_id
+                    && diagnosis.status() != DiagnosisStatus::Deleted
+            })
+            .collect()
+    });
+    diagnoses.sort_by_key(|diagnosis| (diagnosis.timestamp, diagnosis.id));
+
+    let mut previous: Option<u64> = None;
+    diagnoses
+        .into_iter()
+        .map(|diagnosis| {
+            let since_previous_ns = previous.map(|previous| diagnosis.timestamp.saturating_sub(previous));
+            previous = Some(diagnosis.timestamp);
+            TimelineEntry {
+                diagnosis: apply_output_filters(diagnosis, &config),
+                since_previous_ns,
+            }
+        })
+        .collect()
+}
+
+#[query]
+fn find_metadata_conflicts() -> Vec<(String, Vec<u64>)> {
+    let mut by_patient: BTreeMap<String, Vec<(u64, String)>> = BTreeMap::new();
+
+    DIAGNOSES.with(|diagnoses| {
+        for (id, diagnosis) in diagnoses.borrow().iter() {
+            if diagnosis.status() == DiagnosisStatus::Deleted {
+                continue;
+            }
+            let metadata = diagnosis.patient_metadata;
+            by_patient
+                .entry(metadata.anonymized_id)
+                .or_default()
+                .push((id, metadata.age_range));
+        }
+    });
+
+    by_patient
+        .into_iter()
+        .filter(|(_, records)| records.iter().any(|(_, age_range)| *age_range != records[0].1))
+        .map(|(anonymized_id, records)| (anonymized_id, records.into_iter().map(|(id, _)| id).collect()))
+        .collect()
+}
+
+#[update]
+fn get_fda_compliance_report(diagnosis_id: u64) -> Result<ComplianceReport, String> {
+    if require_authenticated().is_err() {
+        record_error("get_fda_compliance_report", &MedicalError::AnonymousCaller);
+        return Err("Anonymous callers cannot request compliance reports".to_string());
+    }
+    let Some(diagnosis) = DIAGNOSES.with(|diagnoses| diagnoses.borrow().get(&diagnosis_id)) else {
+        record_error("get_fda_compliance_report", &MedicalError::DiagnosisNotFound);
+        return Err("Diagnosis not found".to_string());
+    };
+    
+    // Add audit entry for compliance report generation
+    if config().access_logging_enabled {
+        add_audit_entry(
+            diagnosis_id,
+            "COMPLIANCE_REPORT_GENERATED".to_string(),
+            "FDA compliance report requested".to_string(),
+        );
+    }
+    
+    Ok(build_compliance_report(&diagnosis))
+}
+
+#[query]
+fn get_noncompliant_diagnoses(limit: u64) -> Vec<(u64, Vec<String>)> {
+    DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow()
+            .iter()
+            .filter(|(_, diagnosis)| diagnosis.status() != DiagnosisStatus::Deleted)
+            .filter_map(|(id, diagnosis)| {
+                let failures = compliance_failures(&diagnosis);
+                (!failures.is_empty()).then_some((id, failures))
+            })
+            .take(limit as usize)
+            .collect()
+    })
+}
+
+/// Builds one compliance report per ID. An update rather than a query because each report
+/// appends an audit entry when access logging is enabled. Anonymous callers and batches over
+/// `MAX_COMPLIANCE_BATCH_SIZE` fail every item with the same error, recorded once.
+#[update]
+fn get_compliance_reports_batch(ids: Vec<u64>) -> Vec<Result<ComplianceReport, MedicalError>> {
+    let rejection = if require_authenticated().is_err() {
+        Some(MedicalError::AnonymousCaller)
+    } else if ids.len() as u64 > MAX_COMPLIANCE_BATCH_SIZE {
+        Some(MedicalError::BatchTooLarge(MAX_COMPLIANCE_BATCH_SIZE))
+    } else {
+        None
+    };
+    if let Some(error) = rejection {
+        record_error("get_compliance_reports_batch", &error);
+        return ids.iter().map(|_| Err(error.clone())).collect();
+    }
+    let access_logging_enabled = config().access_logging_enabled;
+    ids.into_iter()
+        .map(|diagnosis_id| {
+            let diagnosis = load_diagnosis(diagnosis_id)?;
+
+            if access_logging_enabled {
+                add_audit_entry(
+                    diagnosis_id,
+                    "COMPLIANCE_REPORT_GENERATED".to_string(),
+                    "FDA compliance report requested in batch".to_string(),
+                );
+            }
+
+            Ok(build_compliance_report(&diagnosis))
+        })
+        .map(|result| result.inspect_err(|error| record_error("get_compliance_reports_batch", error)))
+        .collect()
+}
+
+#[query]
+fn get_diagnoses_by_status(status: DiagnosisStatus, limit: u64) -> Vec<MedicalDiagnosisResult> {
+    let config = config();
+    DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow()
+            .iter()
+            .map(|(_, diagnosis)| diagnosis)
+            .filter(|diagnosis| diagnosis.status() == status)
+            .take(limit as usize)
+            .map(|diagnosis| apply_output_filters(diagnosis, &config))
+            .collect()
+    })
+}
+
+/// Counts diagnoses per UTC day in `[start_ns, end_ns)`, keyed by the day's midnight
+/// timestamp. Days without diagnoses are omitted.
+#[query]
+fn get_daily_analysis_counts(start_ns: u64, end_ns: u64) -> Vec<(u64, u64)> {
+    let mut counts: BTreeMap<u64, u64> = BTreeMap::new();
+
+    DIAGNOSES.with(|diagnoses| {
+        for (_, diagnosis) in diagnoses.borrow().iter() {
+            if (start_ns..end_ns).contains(&diagnosis.timestamp) {
+                let day_start = diagnosis.timestamp - diagnosis.timestamp % NANOS_PER_DAY;
+                *counts.entry(day_start).or_default() += 1;
+            }
+        }
+    });
+
+    counts.into_iter().collect()
+}
+
+#[query]
+fn get_diagnoses_by_model(model_version: String, limit: u64) -> Vec<MedicalDiagnosisResult> {
+    let config = config();
+    DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow()
+            .iter()
+            .map(|(_, diagnosis)| diagnosis)
+            .filter(|diagnosis| diagnosis.model_version == model_version)
+            .take(limit as usize)
+            .map(|diagnosis| apply_output_filters(diagnosis, &config))
+            .collect()
+    })
+}
+
+/// Consolidates per-model findings from a consensus run into those a majority agreed on.
+#[query]
+fn compute_finding_agreement(per_model_findings: Vec<Vec<MedicalFinding>>) -> Result<Vec<MedicalFinding>, MedicalError> {
+    if per_model_findings.len() as u64 > MAX_CONSENSUS_MODELS {
+        return Err(MedicalError::BatchTooLarge(MAX_CONSENSUS_MODELS));
+    }
+    Ok(consolidate_findings(&per_model_findings))
+}
+
+/// Diagnoses without both an ICD-10 and a SNOMED code, which cannot be exported cleanly
+/// to billing or FHIR. Covers uncategorized results and records created before coding.
+#[query]
+fn find_diagnoses_missing_codes() -> Vec<u64> {
+    DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow()
+            .iter()
+            .filter(|(_, diagnosis)| {
+                diagnosis.status() != DiagnosisStatus::Deleted
+                    && (diagnosis.icd10_code.is_none() || diagnosis.snomed_code.is_none())
+            })
+            .map(|(id, _)| id)
+            .collect()
+    })
+}
+
+/// The `n` least confident non-deleted diagnoses, ascending by confidence score.
+#[query]
+fn get_lowest_confidence_diagnoses(n: u64) -> Vec<MedicalDiagnosisResult> {
+    let n = n.min(MAX_QA_SAMPLE_SIZE) as usize;
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let mut heap = BinaryHeap::with_capacity(n + 1);
+    DIAGNOSES.with(|diagnoses| {
+        for (id, diagnosis) in diagnoses.borrow().iter() {
+            if diagnosis.status() == DiagnosisStatus::Deleted {
+                continue;
+            }
+            heap.push(ConfidenceRank { confidence: diagnosis.confidence_score, id });
+            if heap.len() > n {
+                heap.pop();
+            }
+        }
+    });
+
+    let config = config();
+    heap.into_sorted_vec()
+        .into_iter()
+        .filter_map(|rank| load_diagnosis(rank.id).ok())
+        .map(|diagnosis| apply_output_filters(diagnosis, &config))
+        .collect()
+}
+
+#[update]
+fn request_review(diagnosis_id: u64, reason: String) -> Result<(), MedicalError> {
+    track_sync_errors("request_review", || {
+        require_admin_or_provider()?;
+        validate_free_text(&[&reason])?;
+        let mut diagnosis = load_diagnosis(diagnosis_id)?;
+
+        if !matches!(diagnosis.status(), DiagnosisStatus::Active | DiagnosisStatus::Amended) {
+            return Err(MedicalError::InvalidStatusTransition(diagnosis.status()));
+        }
+
+        diagnosis.status = Some(DiagnosisStatus::UnderReview);
+
+        add_audit_entry(
+            diagnosis_id,
+            "REVIEW_REQUESTED".to_string(),
+            format!("Diagnosis flagged for review: {}", reason),
+        );
+        assign_reviewer(&mut diagnosis, &config());
+        store_diagnosis(&diagnosis);
+
+        Ok(())
+    })
+}
+
+/// Diagnoses awaiting review that were assigned to the caller, oldest first.
+#[query]
+fn get_my_review_queue() -> Result<Vec<MedicalDiagnosisResult>, MedicalError> {
+    let caller = require_admin_or_provider()?;
+    Ok(DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow()
+            .iter()
+            .map(|(_, diagnosis)| diagnosis)
+            .filter(|diagnosis| {
+                diagnosis.status() == DiagnosisStatus::UnderReview
+                    && diagnosis.assigned_reviewer == Some(caller)
+            })
+            .collect()
+    }))
+}
+
+#[update]
+async fn submit_review(diagnosis_id: u64, decision: ReviewDecision, notes: String) -> Result<(), MedicalError> {
+    track_errors("submit_review", async {
+        let reviewer = require_admin_or_provider()?;
+        validate_free_text(&[&notes])?;
+        let diagnosis = load_diagnosis(diagnosis_id)?;
+
+        if diagnosis.status() != DiagnosisStatus::UnderReview {
+            return Err(MedicalError::InvalidStatusTransition(diagnosis.status()));
+        }
+
+        let timestamp = time();
+        let payload = review_signing_payload(diagnosis_id, decision, reviewer, timestamp);
+        let (review_signature, review_public_key) = sign_diagnosis_payload(&payload, vec![], &config())
+            .await
+            .map_err(MedicalError::SignatureFailed)?;
+
+        // Re-read after the signing call so a concurrent transition is not overwritten
+        let mut diagnosis = load_diagnosis(diagnosis_id)?;
+        if diagnosis.status() != DiagnosisStatus::UnderReview {
+            return Err(MedicalError::InvalidStatusTransition(diagnosis.status()));
+        }
+
+        // A disputed diagnosis stays under review until it is amended
+        if decision == ReviewDecision::Confirmed {
+            diagnosis.status = Some(DiagnosisStatus::Active);
+        }
+        diagnosis.review = Some(ReviewRecord {
+            reviewer,
+            decision,
+            notes: notes.clone(),
+            timestamp,
+            review_signature,
+            review_public_key,
+        });
+        store_diagnosis(&diagnosis);
+
+        add_audit_entry(
+            diagnosis_id,
+            "REVIEW_SUBMITTED".to_string(),
+            format!("Review decision {:?}: {}", decision, notes),
+        );
+
+        Ok(())
+    })
+    .await
+}
+
+#[query]
+fn verify_review(diagnosis_id: u64) -> Result<bool, MedicalError> {
+    let diagnosis = load_diagnosis(diagnosis_id)?;
+    let review = diagnosis.review.ok_or(MedicalError::ReviewNotFound)?;
+    let payload = review_signing_payload(diagnosis_id, review.decision, review.reviewer, review.timestamp);
+
+    Ok(verify_ecdsa_signature(payload.as_bytes(), &review.review_signature, &review.review_public_key))
+}
+
+#[update]
+async fn amend_diagnosis(
+    diagnosis_id: u64,
+    amended_diagnosis: String,
+    reason: String,
+) -> Result<MedicalDiagnosisResult, MedicalError> {
+    track_errors("amend_diagnosis", async {
+        let caller = require_admin_or_provider()?;
+        validate_free_text(&[&amended_diagnosis, &reason])?;
+        let mut original = load_diagnosis(diagnosis_id)?;
+
+        if !matches!(
+            original.status(),
+            DiagnosisStatus::Active | DiagnosisStatus::UnderReview | DiagnosisStatus::Amended
+        ) {
+            return Err(MedicalError::InvalidStatusTransition(original.status()));
+        }
+
+        let timestamp = time();
+        let diagnosis_data = diagnosis_signing_payload(
+            PAYLOAD_SCHEMA_VERSION,
+            &amended_diagnosis,
+            original.confidence_score,
+            original.confidence_bps,
+            timestamp,
+            &original.patient_metadata.anonymized_id,
+        );
+        let config = config();
+        let derivation_path = patient_derivation_path(&original.patient_metadata.anonymized_id, &config);
+        let (signature, public_key) = sign_diagnosis_payload(&diagnosis_data, derivation_path, &config)
+            .await
+            .map_err(MedicalError::SignatureFailed)?;
+
+        // Re-read after the signing call so a concurrent transition is not overwritten
+        let signed_anonymized_id = original.patient_metadata.anonymized_id;
+        original = load_diagnosis(diagnosis_id)?;
+        if matches!(original.status(), DiagnosisStatus::Superseded | DiagnosisStatus::Deleted) {
+            return Err(MedicalError::InvalidStatusTransition(original.status()));
+        }
+        // The signature covers the anonymized ID, so a concurrent metadata update invalidates it
+        if original.patient_metadata.anonymized_id != signed_anonymized_id {
+            return Err(MedicalError::ConcurrentModification);
+        }
+
+        let (icd10_code, snomed_code) = diagnosis_codes(&amended_diagnosis).unzip();
+        let mut amended = MedicalDiagnosisResult {
+            id: next_diagnosis_id(),
+            diagnosis: amended_diagnosis,
+            timestamp,
+            signature,
+            public_key,
+            status: Some(DiagnosisStatus::Amended),
+            review: None,
+            amends: Some(diagnosis_id),
+            source_id: None,
+            assigned_reviewer: None,
+            icd10_code,
+            snomed_code,
+            submitted_by: Some(caller),
+            payload_schema_version: Some(PAYLOAD_SCHEMA_VERSION),
+            signing_curve: Some(config.signing_curve),
+            ..original.clone()
+        };
+        // A longer diagnosis text can push a record that fit before over the storage bound
+        fit_findings(&mut amended, &config)?;
+        store_diagnosis(&amended);
+
+        original.status = Some(DiagnosisStatus::Superseded);
+        store_diagnosis(&original);
+
+        add_audit_entry(
+            diagnosis_id,
+            "DIAGNOSIS_SUPERSEDED".to_string(),
+            format!("Superseded by amended diagnosis {}: {}", amended.id, reason),
+        );
+        add_audit_entry(
+            amended.id,
+            "DIAGNOSIS_AMENDED".to_string(),
+            format!("Amends diagnosis {}: {}", diagnosis_id, reason),
+        );
+
+        Ok(amended)
+    })
+    .await
+}
+
+/// Corrects patient metadata without re-running analysis. Only the submitting provider or an
+/// admin may do this. Changing the anonymized ID re-signs the record, since it is part of the
+/// canonical payload; the original analysis timestamp is kept.
+#[update]
+async fn update_patient_metadata(diagnosis_id: u64, metadata: PatientMetadata) -> Result<(), MedicalError> {
+    track_errors("update_patient_metadata", async {
+        let caller = require_admin_or_provider()?;
+        let diagnosis = load_diagnosis(diagnosis_id)?;
+        if !is_admin(&caller) && diagnosis.submitted_by != Some(caller) {
+            return Err(MedicalError::Unauthorized);
+        }
+        if matches!(diagnosis.status(), DiagnosisStatus::Superseded | DiagnosisStatus::Deleted) {
+            return Err(MedicalError::InvalidStatusTransition(diagnosis.status()));
+        }
+
+        let config = config();
+        let mut metadata = metadata;
+        normalize_acquisition_date(&mut metadata, &config)?;
+        validate_acquisition_date(&metadata, &config, time())?;
+        validate_anonymization(&metadata, &config)?;
+
+        let resign = metadata.anonymized_id != diagnosis.patient_metadata.anonymized_id;
+        let signing_material = if resign {
+            let payload = diagnosis_signing_payload(
+                PAYLOAD_SCHEMA_VERSION,
+                &diagnosis.diagnosis,
+                diagnosis.confidence_score,
+                diagnosis.confidence_bps,
+                diagnosis.timestamp,
+                &metadata.anonymized_id,
+            );
+            let derivation_path = patient_derivation_path(&metadata.anonymized_id, &config);
+            Some(
+                sign_diagnosis_payload(&payload, derivation_path, &config)
+                    .await
+                    .map_err(MedicalError::SignatureFailed)?,
+            )
+        } else {
+            None
+        };
+
+        // Re-read after the signing call so a concurrent transition is not overwritten
+        let previous_anonymized_id = diagnosis.patient_metadata.anonymized_id;
+        let mut diagnosis = load_diagnosis(diagnosis_id)?;
+        if matches!(diagnosis.status(), DiagnosisStatus::Superseded | DiagnosisStatus::Deleted) {
+            return Err(MedicalError::InvalidStatusTransition(diagnosis.status()));
+        }
+        // Both the new signature and the decision to skip re-signing assume the ID read above
+        if diagnosis.patient_metadata.anonymized_id != previous_anonymized_id {
+            return Err(MedicalError::ConcurrentModification);
+        }
+
+        let details = format!(
+            "Metadata updated (study_type {} -> {}, acquisition_date {} -> {}, age_range {} -> {}{})",
+            diagnosis.patient_metadata.study_type,
+            metadata.study_type,
+            diagnosis.patient_metadata.acquisition_date,
+            metadata.acquisition_date,
+            diagnosis.patient_metadata.age_range,
+            metadata.age_range,
+            if resign { ", anonymized_id changed, re-signed" } else { "" },
+        );
+        if let Some((signature, public_key)) = signing_material {
+            diagnosis.signature = signature;
+            diagnosis.public_key = public_key;
+            diagnosis.payload_schema_version = Some(PAYLOAD_SCHEMA_VERSION);
+            diagnosis.signing_curve = Some(config.signing_curve);
+        }
+        if resign {
+            rekey_dedup_entries(diagnosis_id, &previous_anonymized_id, &metadata.anonymized_id);
+        }
+        diagnosis.patient_metadata = metadata;
+        store_diagnosis(&diagnosis);
+
+        add_audit_entry(diagnosis_id, "METADATA_UPDATED".to_string(), details);
+
+        Ok(())
+    })
+    .await
+}
+
+#[update]
+fn delete_diagnosis(diagnosis_id: u64) -> Result<(), MedicalError> {
+    track_sync_errors("delete_diagnosis", || {
+        require_admin_or_provider()?;
+        // Repeat deletions succeed without touching the record or the audit trail
+        if is_deleted(diagnosis_id) {
+            return Ok(());
+        }
+        let mut diagnosis = load_diagnosis(diagnosis_id)?;
+        if diagnosis.status() == DiagnosisStatus::Deleted {
+            DELETED_DIAGNOSES.with(|deleted| deleted.borrow_mut().insert(diagnosis_id, ()));
+            return Ok(());
+        }
+
+        // Records are retained with a Deleted status so the audit trail stays resolvable
+        diagnosis.status = Some(DiagnosisStatus::Deleted);
+        store_diagnosis(&diagnosis);
+        DELETED_DIAGNOSES.with(|deleted| deleted.borrow_mut().insert(diagnosis_id, ()));
+
+        add_audit_entry(
+            diagnosis_id,
+            "DIAGNOSIS_DELETED".to_string(),
+            "Diagnosis marked as deleted".to_string(),
+        );
+
+        Ok(())
+    })
+}
+
+#[query]
+fn is_deleted(diagnosis_id: u64) -> bool {
+    DELETED_DIAGNOSES.with(|deleted| deleted.borrow().contains_key(&diagnosis_id))
+}
+
+#[update]
+fn import_diagnosis(result: MedicalDiagnosisResult) -> Result<u64, MedicalError> {
+    track_sync_errors("import_diagnosis", || {
+        require_admin()?;
+        let config = config();
+        validate_model_version(&result.model_version, &config)?;
+
+        if !verify_stored_signature(&result) {
+            return Err(MedicalError::InvalidSignature);
+        }
+        // The lineage behind these statuses stays on the source canister
+        if matches!(result.status(), DiagnosisStatus::Superseded | DiagnosisStatus::Deleted) {
+            return Err(MedicalError::InvalidStatusTransition(result.status()));
+        }
+        // Findings and the acquisition date are outside the signed payload, so truncation and
+        // normalization leave the signature valid
+        let mut result = result;
+        normalize_acquisition_date(&mut result.patient_metadata, &config)?;
+        validate_acquisition_date(&result.patient_metadata, &config, time())?;
+        validate_anonymization(&result.patient_metadata, &config)?;
+        let truncated_findings = fit_findings(&mut result, &config)?;
+
+        // Keep the ID from the canister that first produced the record
+        let source_id = result.source_id.unwrap_or(result.id);
+        let imported = MedicalDiagnosisResult {
+            id: next_diagnosis_id(),
+            source_id: Some(source_id),
+            // Review and amendment links refer to IDs on the source canister
+            review: None,
+            amends: None,
+            assigned_reviewer: None,
+            submitted_by: None,
+            ..result
+        };
+        store_diagnosis(&imported);
+
+        add_audit_entry(
+            imported.id,
+            "DIAGNOSIS_IMPORTED".to_string(),
+            format!("Imported signed diagnosis with source ID {}", source_id),
+        );
+        if truncated_findings > 0 {
+            add_audit_entry(
+                imported.id,
+                "FINDINGS_TRUNCATED".to_string(),
+                format!("Dropped {} lowest-priority findings to fit storage limits", truncated_findings),
+            );
+        }
+
+        Ok(imported.id)
+    })
+}
+
+#[update]
+async fn get_negative_attestation(diagnosis_id: u64) -> Result<(String, Vec<u8>), MedicalError> {
+    track_errors("get_negative_attestation", async {
+        let caller = require_authenticated()?;
+        let diagnosis = load_diagnosis(diagnosis_id)?;
+
+        // A result awaiting review may yet be disputed, so it cannot be attested either
+        if matches!(
+            diagnosis.status(),
+            DiagnosisStatus::UnderReview | DiagnosisStatus::Superseded | DiagnosisStatus::Deleted
+        ) {
+            return Err(MedicalError::InvalidStatusTransition(diagnosis.status()));
+        }
+        if !is_normal_diagnosis(&diagnosis.diagnosis)
+            || findings_contradiction(&diagnosis.diagnosis, &diagnosis.medical_findings).is_some()
+        {
+            return Err(MedicalError::NotNormalDiagnosis);
+        }
+        if !config().signing_enabled {
+            return Err(MedicalError::SigningDisabled);
+        }
+        check_signing_rate_limit(caller)?;
+
+        let statement = format!(
+            "NEGATIVE ATTESTATION: Diagnosis {} for patient {} ({} acquired {}) was analyzed by {} at {} with no abnormal findings detected.",
+            diagnosis.id,
+            diagnosis.patient_metadata.anonymized_id,
+            diagnosis.patient_metadata.study_type,
+            diagnosis.patient_metadata.acquisition_date,
+            diagnosis.model_version,
+            diagnosis.timestamp,
+        );
+        let (signature, _) = create_cryptographic_signature(statement.as_bytes(), vec![])
+            .await
+            .map_err(MedicalError::SignatureFailed)?;
+
+        add_audit_entry(
+            diagnosis_id,
+            "NEGATIVE_ATTESTATION_ISSUED".to_string(),
+            "Signed no-findings attestation issued".to_string(),
+        );
+
+        Ok((statement, signature))
+    })
+    .await
+}
+
+#[update]
+async fn sign_data(data: Vec<u8>) -> Result<(Vec<u8>, Vec<u8>), MedicalError> {
+    track_errors("sign_data", async {
+        let caller = require_admin_or_provider()?;
+        if !config().signing_enabled {
+            return Err(MedicalError::SigningDisabled);
+        }
+        check_signing_rate_limit(caller)?;
+
+        let (signature, public_key) = create_cryptographic_signature(&data, vec![])
+            .await
+            .map_err(MedicalError::SignatureFailed)?;
+
+        // Detached signatures are not tied to a diagnosis, so they are logged against ID 0
+        add_audit_entry(
+            0,
+            "DATA_SIGNED".to_string(),
+            format!("Detached signature issued over {} bytes (sha256 {})", data.len(), hex::encode(Sha256::digest(&data))),
+        );
+
+        Ok((signature, public_key))
+    })
+    .await
+}
+
+/// Verifies a stored diagnosis on behalf of another canister and signs the verdict.
+/// The returned attestation is the 17-byte signed payload followed by the 64-byte signature,
+/// checkable against this canister's ECDSA public key.
+#[update]
+async fn attest_verification(diagnosis_id: u64) -> Result<(bool, Vec<u8>), MedicalError> {
+    track_errors("attest_verification", async {
+        let caller = require_authenticated()?;
+        if !config().signing_enabled {
+            return Err(MedicalError::SigningDisabled);
+        }
+        check_signing_rate_limit(caller)?;
+
+        let verified = verify_stored_signature(&load_verifiable_diagnosis(diagnosis_id)?);
+        let mut attestation = attestation_payload(diagnosis_id, verified, time());
+        let (signature, _) = create_cryptographic_signature(&attestation, vec![])
+            .await
+            .map_err(MedicalError::SignatureFailed)?;
+        attestation.extend_from_slice(&signature);
+
+        add_audit_entry(
+            diagnosis_id,
+            "VERIFICATION_ATTESTED".to_string(),
+            format!("Signature verdict {} attested for {}", verified, caller),
+        );
+
+        Ok((verified, attestation))
+    })
+    .await
+}
+
+#[query]
+fn verify_data(data: Vec<u8>, signature: Vec<u8>, public_key: Vec<u8>) -> bool {
+    verify_ecdsa_signature(&data, &signature, &public_key)
+}
+
+#[query]
+fn get_config() -> CanisterConfig {
+    config()
+}
+
+/// Signs the current config so auditors can attest to the exact state at a point in time.
+/// This is an update call because threshold signing is unavailable to queries.
+#[update]
+async fn get_signed_config_snapshot() -> Result<(CanisterConfig, u64, Vec<u8>), MedicalError> {
+    track_errors("get_signed_config_snapshot", async {
+        let caller = require_authenticated()?;
+        let config = config();
+        if !config.signing_enabled {
+            return Err(MedicalError::SigningDisabled);
+        }
+        check_signing_rate_limit(caller)?;
+
+        let timestamp = time();
+        let (signature, _) = create_cryptographic_signature(&config_snapshot_payload(&config, timestamp), vec![])
+            .await
+            .map_err(MedicalError::SignatureFailed)?;
+
+        add_audit_entry(
+            0,
+            "CONFIG_SNAPSHOT_SIGNED".to_string(),
+            format!("Config snapshot at {} signed for {}", timestamp, caller),
+        );
+
+        Ok((config, timestamp, signature))
+    })
+    .await
+}
+
+#[update]
+fn update_config(new_config: CanisterConfig) -> Result<(), MedicalError> {
+    track_sync_errors("update_config", || {
+        let admin = require_admin()?;
+        new_config.signing_curve.ecdsa_curve().map_err(MedicalError::InvalidConfig)?;
+        let old_config = CONFIG.with(|config| config.borrow_mut().set(new_config.clone()))
+            .map_err(|e| MedicalError::InvalidConfig(format!("{:?}", e)))?;
+
+        CONFIG_HISTORY.with(|history| {
+            let mut history = history.borrow_mut();
+            let id = history.last_key_value().map_or(1, |(id, _)| id + 1);
+            history.insert(id, ConfigChange {
+                id,
+                timestamp: time(),
+                admin,
+                old_config,
+                new_config,
+            });
+        });
+
+        Ok(())
+    })
+}
+
+#[query]
+fn get_config_history(limit: u64) -> Vec<ConfigChange> {
+    CONFIG_HISTORY.with(|history| {
+        history.borrow()
+            .iter()
+            .rev()
+            .take(limit as usize)
+            .map(|(_, change)| change)
+            .collect()
+    })
+}
+
+#[update]
+fn record_consent(record: ConsentRecord) -> Result<(), MedicalError> {
+    track_sync_errors("record_consent", || {
+        require_admin_or_provider()?;
+        store_consent(record)
+    })
+}
+
+#[update]
+fn record_consent_batch(records: Vec<ConsentRecord>) -> Vec<Result<(), MedicalError>> {
+    if let Err(e) = require_admin_or_provider() {
+        record_error("record_consent_batch", &e);
+        return records.iter().map(|_| Err(e.clone())).collect();
+    }
+
+    records
+        .into_iter()
+        .enumerate()
+        .map(|(index, record)| {
+            if index as u64 >= MAX_CONSENT_BATCH_SIZE {
+                return Err(MedicalError::BatchTooLarge(MAX_CONSENT_BATCH_SIZE));
+            }
+            store_consent(record)
+        })
+        .map(|result| result.inspect_err(|error| record_error("record_consent_batch", error)))
+        .collect()
+}
+
+#[query]
+fn get_consent(anonymized_id: String) -> Option<ConsentRecord> {
+    CONSENTS.with(|consents| consents.borrow().get(&anonymized_id))
+}
+
+/// Normalizes stored finding severities to canonical levels for up to `limit` diagnoses
+/// from `start_id`, applying the configured policy to values that cannot be mapped. Pass
+/// `next_cursor` as `start_id` to continue; it is `None` once every diagnosis has been visited.
+#[update]
+fn migrate_severities(start_id: u64, limit: u64) -> Result<SeverityMigrationResult, MedicalError> {
+    track_sync_errors("migrate_severities", || {
+        require_admin()?;
+        let policy = config().unmapped_severity_policy;
+        let limit = limit.min(MAX_SEVERITY_MIGRATION_BATCH_SIZE) as usize;
+        let mut migrated = 0;
+
+        let (diagnoses, next_cursor) = DIAGNOSES.with(|diagnoses| {
+            let diagnoses = diagnoses.borrow();
+            let mut window = diagnoses.range(start_id..);
+            let batch: Vec<MedicalDiagnosisResult> = window.by_ref().take(limit).map(|(_, d)| d).collect();
+            (batch, window.next().map(|(id, _)| id))
+        });
+        for mut diagnosis in diagnoses {
+            let original: Vec<String> = diagnosis.medical_findings.iter().map(|f| f.severity.clone()).collect();
+            let needs_review = normalize_severities(&mut diagnosis.medical_findings, policy);
+
+            UNMAPPED_SEVERITIES.with(|unmapped| {
+                let mut unmapped = unmapped.borrow_mut();
+                if needs_review {
+                    unmapped.insert(diagnosis.id, ());
+                } else {
+                    unmapped.remove(&diagnosis.id);
+                }
+            });
+            if diagnosis.medical_findings.iter().map(|f| &f.severity).ne(original.iter()) {
+                store_diagnosis(&diagnosis);
+                migrated += 1;
+            }
+        }
+
+        add_audit_entry(
+            0,
+            "SEVERITY_MIGRATION".to_string(),
+            format!("Normalized severities on {} diagnoses from ID {} using {:?}", migrated, start_id, policy),
+        );
+
+        Ok(SeverityMigrationResult { migrated, next_cursor })
+    })
+}
+
+#[query]
+fn get_unmapped_severities(limit: u64) -> Vec<u64> {
+    UNMAPPED_SEVERITIES.with(|unmapped| unmapped.borrow().iter().map(|(id, _)| id).take(limit as usize).collect())
+}
+
+#[update]
+fn register_model(version: String, description: String, fda_cleared: bool, active: bool) -> Result<(), MedicalError> {
+    track_sync_errors("register_model", || {
+        require_admin()?;
+        let record = ModelRecord {
+            version: version.clone(),
+            description,
+            fda_cleared,
+            active,
+            registered_at: time(),
+        };
+        MODEL_REGISTRY.with(|registry| registry.borrow_mut().insert(version, record));
+        Ok(())
+    })
+}
+
+#[query]
+fn get_registered_models() -> Vec<ModelRecord> {
+    MODEL_REGISTRY.with(|registry| {
+        registry.borrow().iter().map(|(_, record)| record).collect()
+    })
+}
+
+#[query]
+fn run_compliance_self_audit() -> ComplianceSelfAudit {
+    let config = config();
+    let mut checks = Vec::new();
+
+    let cleared_model_active = MODEL_REGISTRY.with(|registry| {
+        registry.borrow().iter().any(|(_, model)| model.active && model.fda_cleared)
+    });
+    checks.push(ComplianceCheck {
+        name: "FDA_CLEARED_MODEL_ACTIVE".to_string(),
+        passed: cleared_model_active,
+        remediation: (!cleared_model_active).then(|| {
+            "Register an FDA-cleared model version and mark it active via register_model".to_string()
+        }),
+    });
+
+    let production_signing = config.signing_enabled
+        && !TEST_ECDSA_KEY_NAMES.contains(&config.ecdsa_key_name.as_str());
+    checks.push(ComplianceCheck {
+        name: "PRODUCTION_SIGNING_KEY".to_string(),
+        passed: production_signing,
+        remediation: (!production_signing).then(|| {
+            "Enable signing and set ecdsa_key_name to a production threshold ECDSA key (e.g. key_1)".to_string()
+        }),
+    });
+
+    let retention_configured = config.retention_period_days.is_some();
+    checks.push(ComplianceCheck {
+        name: "RETENTION_POLICY".to_string(),
+        passed: retention_configured,
+        remediation: (!retention_configured).then(|| {
+            "Set retention_period_days to the record retention period required by your jurisdiction".to_string()
+        }),
+    });
+
+    checks.push(ComplianceCheck {
+        name: "ACCESS_LOGGING".to_string(),
+        passed: config.access_logging_enabled,
+        remediation: (!config.access_logging_enabled).then(|| {
+            "Enable access_logging_enabled so report requests are recorded in the audit trail".to_string()
+        }),
+    });
+
+    ComplianceSelfAudit {
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+        generated_timestamp: time(),
+    }
+}
+
+#[update]
+fn add_admin(principal: Principal) -> Result<(), MedicalError> {
+    track_sync_errors("add_admin", || {
+        require_admin()?;
+        if principal == Principal::anonymous() {
+            return Err(MedicalError::AnonymousCaller);
+        }
+        ADMINS.with(|admins| admins.borrow_mut().insert(principal, ()));
+        Ok(())
+    })
+}
+
+#[update]
+fn add_provider(principal: Principal) -> Result<(), MedicalError> {
+    track_sync_errors("add_provider", || {
+        require_admin()?;
+        if principal == Principal::anonymous() {
+            return Err(MedicalError::AnonymousCaller);
+        }
+        PROVIDERS.with(|providers| providers.borrow_mut().insert(principal, ()));
+        Ok(())
+    })
+}
+
+/// Moves up to `limit` diagnoses older than `archive_after_days` into the archive.
+/// Records still under review stay in the hot map. Returns the number archived.
+/// Archival is operator-triggered: no timer calls this, so schedule it externally.
+#[update]
+fn archive_old_diagnoses(limit: u64) -> Result<u64, MedicalError> {
+    track_sync_errors("archive_old_diagnoses", || {
+        require_admin()?;
+        let days = config().archive_after_days
+            .ok_or_else(|| MedicalError::InvalidConfig("archive_after_days is not set".to_string()))?;
+        let cutoff = time().saturating_sub(days.saturating_mul(NANOS_PER_DAY));
+
+        let expired: Vec<u64> = DIAGNOSES.with(|diagnoses| {
+            diagnoses.borrow()
+                .iter()
+                .filter(|(_, diagnosis)| diagnosis.timestamp < cutoff && diagnosis.status() != DiagnosisStatus::UnderReview)
+                .map(|(id, _)| id)
+                .take(limit.min(MAX_ARCHIVAL_BATCH_SIZE) as usize)
+                .collect()
+        });
+
+        for id in &expired {
+            if let Some(diagnosis) = DIAGNOSES.with(|diagnoses| diagnoses.borrow_mut().remove(id)) {
+                ARCHIVED_DIAGNOSES.with(|archive| archive.borrow_mut().insert(*id, diagnosis));
+            }
+        }
+
+        if !expired.is_empty() {
+            add_audit_entry(
+                0,
+                "DIAGNOSES_ARCHIVED".to_string(),
+                format!("Archived {} diagnoses older than {} days", expired.len(), days),
+            );
+        }
+
+        Ok(expired.len() as u64)
+    })
+}
+
+/// Evicts expired image dedup entries, scanning up to `limit` entries per call.
+/// Returns the number evicted; call repeatedly to cover the whole map.
+#[update]
+fn prune_dedup_entries(limit: u64) -> Result<u64, MedicalError> {
+    track_sync_errors("prune_dedup_entries", || {
+        require_admin()?;
+        Ok(sweep_dedup_entries(limit.min(MAX_DEDUP_PRUNE_BATCH_SIZE), &config()))
+    })
+}
+
+#[query]
+fn get_archived_diagnosis(diagnosis_id: u64) -> Option<MedicalDiagnosisResult> {
+    let config = config();
+    ARCHIVED_DIAGNOSES.with(|archive| archive.borrow().get(&diagnosis_id))
+        .map(|diagnosis| apply_output_filters(diagnosis, &config))
+}
+
+/// Most recent endpoint errors, newest first. Admin-only since callers and details can leak.
+#[query]
+fn get_recent_errors(limit: u64) -> Result<Vec<ErrorEvent>, MedicalError> {
+    require_admin()?;
+    Ok(RECENT_ERRORS.with(|errors| errors.borrow().iter().rev().take(limit as usize).cloned().collect()))
+}
+
+/// Latency distribution over diagnoses with persisted metrics, for performance reporting.
+#[query]
+fn get_processing_time_percentiles() -> ProcessingTimePercentiles {
+    let (mut inference, mut processing): (Vec<u64>, Vec<u64>) = DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow()
+            .iter()
+            .filter_map(|(_, diagnosis)| diagnosis.metrics)
+            .map(|metrics| (metrics.model_inference_time_ms, metrics.processing_time_ms))
+            .unzip()
+    });
+    inference.sort_unstable();
+    processing.sort_unstable();
+
+    ProcessingTimePercentiles {
+        sample_count: inference.len() as u64,
+        model_inference_time: latency_percentiles(&inference),
+        processing_time: latency_percentiles(&processing),
+    }
+}
+
+/// A single hash over the diagnosis Merkle root (hot then archived records), the audit
+/// chain head, the config, and the payload schema version, for watchdogs to poll.
+#[query]
+fn get_integrity_fingerprint() -> Vec<u8> {
+    let mut leaves: Vec<[u8; 32]> = DIAGNOSES.with(|diagnoses| {
+        diagnoses.borrow().iter().map(|(id, diagnosis)| diagnosis_leaf(id, &diagnosis)).collect()
+    });
+    ARCHIVED_DIAGNOSES.with(|archive| {
+        leaves.extend(archive.borrow().iter().map(|(id, diagnosis)| diagnosis_leaf(id, &diagnosis)));
+    });
+    let chain_head = AUDIT_TRAIL.with(|trail| {
+        trail.borrow().last_key_value().map(|(_, entry)| audit_chain_hash(&entry)).unwrap_or_default()
+    });
+    let config_hash = Sha256::digest(candid::encode_one(config()).unwrap());
+
+    let mut hasher = Sha256::new();
+    hasher.update(merkle_root(leaves));
+    hasher.update(&chain_head);
+    hasher.update(config_hash);
+    hasher.update(PAYLOAD_SCHEMA_VERSION.to_be_bytes());
+    hasher.finalize().to_vec()
+}
+
+#[query]
+fn get_storage_stats() -> StorageStats {
+    StorageStats {
+        diagnoses: DIAGNOSES.with(|diagnoses| {
+            record_type_stats(diagnoses.borrow().iter().map(|(_, diagnosis)| diagnosis))
+        }),
+        audit_entries: AUDIT_TRAIL.with(|trail| {
+            record_type_stats(trail.borrow().iter().map(|(_, entry)| entry))
+        }),
+    }
+}
+
+/// Estimates time until stable memory is exhausted. Growth is measured from the encoded size
+/// of diagnoses and audit entries written in the trailing window, so allocator overhead is
+/// reflected in `used_bytes` but not in the growth rate.
+#[query]
+fn estimate_storage_runway() -> StorageRunway {
+    let now = time();
+    let window_start = now.saturating_sub(STORAGE_GROWTH_WINDOW_DAYS * NANOS_PER_DAY);
+    let mut recent_bytes = 0u64;
+    let mut earliest_recent = now;
+
+    DIAGNOSES.with(|diagnoses| {
+        for (_, diagnosis) in diagnoses.borrow().iter().filter(|(_, d)| d.timestamp >= window_start) {
+            recent_bytes += diagnosis.to_bytes().len() as u64;
+            earliest_recent = earliest_recent.min(diagnosis.timestamp);
+        }
+    });
+    AUDIT_TRAIL.with(|trail| {
+        for (_, entry) in trail.borrow().iter().filter(|(_, e)| e.timestamp >= window_start) {
+            recent_bytes += entry.to_bytes().len() as u64;
+            earliest_recent = earliest_recent.min(entry.timestamp);
+        }
+    });
+
+    // A young canister is measured over its actual lifetime, but never less than a day
+    let observed_days = ((now - earliest_recent) / NANOS_PER_DAY).max(1);
+    let growth_bytes_per_day = recent_bytes / observed_days;
+
+    let used_bytes = stable_size() * ic_cdk::stable::WASM_PAGE_SIZE_IN_BYTES;
+    let capacity_bytes = config().storage_capacity_bytes;
+    let estimated_days_to_full = (growth_bytes_per_day > 0)
+        .then(|| capacity_bytes.saturating_sub(used_bytes) / growth_bytes_per_day);
+
+    StorageRunway {
+        used_bytes,
+        capacity_bytes,
+        growth_bytes_per_day,
+        estimated_days_to_full,
+        estimated_full_timestamp: estimated_days_to_full
+            .map(|days| now.saturating_add(days.saturating_mul(NANOS_PER_DAY))),
+    }
+}
+
+/// Returns the service definition generated by `export_candid!` for dynamic client binding.
+#[query]
+fn get_candid_interface() -> String {
+    __export_service()
+}
+
+#[update]
+async fn get_access_overview() -> Result<AccessOverview, MedicalError> {
+    track_errors("get_access_overview", async {
+        require_admin()?;
+
+        let info = canister_info(&CanisterInfoArgs {
+            canister_id: canister_self(),
+            num_requested_changes: None,
+        })
+        .await
+        .map_err(|e| MedicalError::ManagementCallFailed(format!("{:?}", e)))?;
+
+        let admins: Vec<Principal> = ADMINS.with(|admins| admins.borrow().iter().map(|(p, _)| p).collect());
+        let providers: Vec<Principal> = PROVIDERS.with(|providers| providers.borrow().iter().map(|(p, _)| p).collect());
+        let admins_not_controllers = admins
+            .iter()
+            .filter(|admin| !info.controllers.contains(admin))
+            .cloned()
+            .collect();
+
+        Ok(AccessOverview {
+            controllers: info.controllers,
+            admins,
+            providers,
+            admins_not_controllers,
+        })
+    })
+    .await
+}
+
+#[query]
+fn get_system_health() -> String {
+    let diagnosis_count = DIAGNOSES.with(|diagnoses| diagnoses.borrow().len());
+    let audit_count = AUDIT_TRAIL.with(|trail| trail.borrow().len());
+    
+    format!(
+        "Medical AI System Status: HEALTHY | Diagnoses: {} | Audit Entries: {} | Model: {}",
+        diagnosis_count, audit_count, MODEL_VERSION
+    )
+}
+
+// Canister lifecycle
+/// Makes `principal` the first admin unless the admin set is already populated.
+fn bootstrap_admin(principal: Principal) {
+    ADMINS.with(|admins| {
+        let mut admins = admins.borrow_mut();
+        if admins.is_empty() {
+            admins.insert(principal, ());
+        }
+    });
+}
+
+#[init]
+fn init() {
+    // The installing principal bootstraps the admin set
+    bootstrap_admin(msg_caller());
+    ic_cdk::println!("Medical AI Backend Canister Initialized");
+}
+
+#[pre_upgrade]
+fn pre_upgrade() {
+    ic_cdk::println!("Medical AI Backend: Pre-upgrade hook called");
+}
+
+#[post_upgrade]
+fn post_upgrade() {
+    // Canisters upgraded from before the admin set existed would otherwise have no admin,
+    // and the upgrading controller is the only principal that can be trusted with it
+    bootstrap_admin(msg_caller());
+    ic_cdk::println!("Medical AI Backend: Post-upgrade hook called");
+}
+
+// Export Candid interface
+ic_cdk::export_candid!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    /// Native stand-ins for the system API and management canister. The clock and caller are
+    /// set per test, and threshold ECDSA is replaced by deterministic local keys derived from
+    /// the key name and derivation path, so signatures verify exactly as on the IC.
+    pub(crate) mod ic {
+        use super::*;
+        use ic_cdk::management_canister::{CanisterInfoResult, EcdsaPublicKeyResult, SignWithEcdsaResult};
+        use k256::ecdsa::signature::hazmat::PrehashSigner;
+        use k256::ecdsa::SigningKey;
+        use std::cell::Cell;
+        use std::pin::Pin;
+
+        pub const START_TIME: u64 = 1_750_000_000_000_000_000;
+
+        thread_local! {
+            static TIME: Cell<u64> = const { Cell::new(START_TIME) };
+            static CALLER: Cell<Principal> = const { Cell::new(Principal::anonymous()) };
+            static SPAWNED: RefCell<Vec<Pin<Box<dyn Future<Output = ()>>>>> = RefCell::new(Vec::new());
+            static SENT: RefCell<Vec<(Principal, String, Vec<u8>)>> = const { RefCell::new(Vec::new()) };
+            static CONTROLLERS: RefCell<Vec<Principal>> = const { RefCell::new(Vec::new()) };
+            static UNREACHABLE: RefCell<Vec<Principal>> = const { RefCell::new(Vec::new()) };
+            static KEY_IDS: RefCell<Vec<EcdsaKeyId>> = const { RefCell::new(Vec::new()) };
+            static INTERLEAVED: RefCell<Option<Box<dyn FnOnce()>>> = const { RefCell::new(None) };
+        }
+
+        pub fn time() -> u64 {
+            TIME.with(Cell::get)
+        }
+
+        pub fn advance_time(ns: u64) {
+            TIME.with(|time| time.set(time.get() + ns));
+        }
+
+        pub fn msg_caller() -> Principal {
+            CALLER.with(Cell::get)
+        }
+
+        pub fn set_caller(principal: Principal) {
+            CALLER.with(|caller| caller.set(principal));
+        }
+
+        pub fn canister_self() -> Principal {
+            Principal::from_slice(&[0xCA, 0xFE])
+        }
+
+        pub fn stable_size() -> u64 {
+            // 10 GiB worth of 64 KiB pages
+            10 * 16 * 1024
+        }
+
+        pub fn set_controllers(controllers: Vec<Principal>) {
+            CONTROLLERS.with(|current| *current.borrow_mut() = controllers);
+        }
+
+        pub async fn canister_info(_arg: &CanisterInfoArgs) -> Result<CanisterInfoResult, String> {
+            Ok(CanisterInfoResult {
+                total_num_changes: 0,
+                recent_changes: Vec::new(),
+                module_hash: None,
+                controllers: CONTROLLERS.with(|controllers| controllers.borrow().clone()),
+            })
+        }
+
+        /// The local key a threshold key name and derivation path resolve to.
+        pub fn signing_key(key_id: &EcdsaKeyId, derivation_path: &[Vec<u8>]) -> SigningKey {
+            let mut hasher = Sha256::new();
+            hasher.update(key_id.name.as_bytes());
+            for component in derivation_path {
+                hasher.update((component.len() as u64).to_be_bytes());
+                hasher.update(component);
+            }
+            SigningKey::from_bytes(&hasher.finalize()).unwrap()
+        }
+
+        pub fn public_key(key_id: &EcdsaKeyId, derivation_path: &[Vec<u8>]) -> Vec<u8> {
+            signing_key(key_id, derivation_path).verifying_key().to_encoded_point(true).as_bytes().to_vec()
+        }
+
+        /// Threshold keys requested so far, by public key lookups and signing calls alike.
+        pub fn key_ids() -> Vec<EcdsaKeyId> {
+            KEY_IDS.with(|key_ids| key_ids.borrow().clone())
+        }
+
+        pub async fn ecdsa_public_key(arg: &EcdsaPublicKeyArgs) -> Result<EcdsaPublicKeyResult, String> {
+            KEY_IDS.with(|key_ids| key_ids.borrow_mut().push(arg.key_id.clone()));
+            Ok(EcdsaPublicKeyResult {
+                public_key: public_key(&arg.key_id, &arg.derivation_path),
+                chain_code: Vec::new(),
+            })
+        }
+
+        /// Runs `message` while the next signing call is in flight, as another update could.
+        pub fn interleave_next_sign(message: impl FnOnce() + 'static) {
+            INTERLEAVED.with(|interleaved| *interleaved.borrow_mut() = Some(Box::new(message)));
+        }
+
+        pub async fn sign_with_ecdsa(arg: &SignWithEcdsaArgs) -> Result<SignWithEcdsaResult, String> {
+            if let Some(message) = INTERLEAVED.with(|interleaved| interleaved.borrow_mut().take()) {
+                message();
+            }
+            KEY_IDS.with(|key_ids| key_ids.borrow_mut().push(arg.key_id.clone()));
+            let signature: Signature = signing_key(&arg.key_id, &arg.derivation_path)
+                .sign_prehash(&arg.message_hash)
+                .map_err(|e| e.to_string())?;
+            Ok(SignWithEcdsaResult {
+                signature: signature.to_bytes().to_vec(),
+            })
+        }
+
+        pub fn spawn<F: 'static + Future<Output = ()>>(future: F) {
+            SPAWNED.with(|spawned| spawned.borrow_mut().push(Box::pin(future)));
+        }
+
+        /// Runs background tasks queued by `spawn`, as the executor would after the call returns.
+        pub fn run_spawned() {
+            let tasks = SPAWNED.with(|spawned| std::mem::take(&mut *spawned.borrow_mut()));
+            for task in tasks {
+                block_on(task);
+            }
+        }
+
+        pub struct Call {
+            target: Principal,
+            method: String,
+            arg: Vec<u8>,
+        }
+
+        impl Call {
+            pub fn bounded_wait(target: Principal, method: &str) -> Self {
+                Self {
+                    target,
+                    method: method.to_string(),
+                    arg: Vec::new(),
+                }
+            }
+
+            pub fn with_arg<A: CandidType>(self, arg: A) -> Self {
+                Self {
+                    arg: candid::encode_one(arg).unwrap(),
+                    ..self
+                }
+            }
+
+            pub fn oneway(self) -> Result<(), String> {
+                if UNREACHABLE.with(|unreachable| unreachable.borrow().contains(&self.target)) {
+                    return Err(format!("{} is unreachable", self.target));
+                }
+                SENT.with(|sent| sent.borrow_mut().push((self.target, self.method, self.arg)));
+                Ok(())
+            }
+        }
+
+        pub fn set_unreachable(target: Principal) {
+            UNREACHABLE.with(|unreachable| unreachable.borrow_mut().push(target));
+        }
+
+        /// One-way calls sent so far, as `(target, method, encoded argument)`.
+        pub fn sent() -> Vec<(Principal, String, Vec<u8>)> {
+            SENT.with(|sent| sent.borrow().clone())
+        }
+    }
+
+    /// Polls a future that never suspends, which holds for every endpoint under the stand-ins.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future suspended without a pending call"),
+        }
+    }
+
+    fn principal(id: u8) -> Principal {
+        Principal::from_slice(&[id])
+    }
+
+    fn admin() -> Principal {
+        principal(1)
+    }
+
+    fn provider() -> Principal {
+        principal(2)
+    }
+
+    fn outsider() -> Principal {
+        principal(3)
+    }
+
+    /// Registers an admin and a provider and leaves the admin as caller.
+    fn setup() {
+        bootstrap_admin(admin());
+        PROVIDERS.with(|providers| providers.borrow_mut().insert(provider(), ()));
+        ic::set_caller(admin());
+    }
+
+    fn configure(change: impl FnOnce(&mut CanisterConfig)) {
+        let mut updated = config();
+        change(&mut updated);
+        CONFIG.with(|config| config.borrow_mut().set(updated).unwrap());
+    }
+
+    fn metadata(anonymized_id: &str) -> PatientMetadata {
+        PatientMetadata {
+            anonymized_id: anonymized_id.to_string(),
+            age_range: "40-49".to_string(),
+            study_type: "Chest X-ray".to_string(),
+            acquisition_date: "2024-01-15".to_string(),
+        }
+    }
+
+    /// A distinct valid image per seed.
+    fn image(seed: u8) -> Vec<u8> {
+        vec![seed; 2048]
+    }
+
+    fn analyze(seed: u8, anonymized_id: &str) -> MedicalDiagnosisResult {
+        block_on(analyze_medical_image(image(seed), metadata(anonymized_id))).unwrap()
+    }
+
+    fn finding(name: &str, severity: &str, confidence: f32) -> MedicalFinding {
+        MedicalFinding {
+            finding: name.to_string(),
+            location: "Right lower lobe".to_string(),
+            severity: severity.to_string(),
+            confidence,
+            raw_confidence: Some(confidence),
+            agreement_count: None,
+        }
+    }
+
+    /// An unsigned record for seeding storage directly; callers adjust fields and store it.
+    fn record(id: u64, anonymized_id: &str) -> MedicalDiagnosisResult {
+        MedicalDiagnosisResult {
+            id,
+            diagnosis: "Pneumonia detected in right lower lobe".to_string(),
+            confidence_score: 0.87,
+            raw_confidence_score: Some(0.87),
+            unadjusted_confidence_score: Some(0.87),
+            medical_findings: vec![finding("Consolidation", "Moderate", 0.87)],
+            timestamp: ic::START_TIME,
+            signature: Vec::new(),
+            public_key: Vec::new(),
+            fda_compliant: true,
+            hipaa_compliant: true,
+            model_version: MODEL_VERSION.to_string(),
+            patient_metadata: metadata(anonymized_id),
+            status: Some(DiagnosisStatus::Active),
+            review: None,
+            amends: None,
+            source_id: None,
+            assigned_reviewer: None,
+            confidence_bps: None,
+            icd10_code: Some("J18.9".to_string()),
+            snomed_code: Some("233604007".to_string()),
+            submitted_by: Some(provider()),
+            metrics: None,
+            payload_schema_version: Some(PAYLOAD_SCHEMA_VERSION),
+            signing_curve: Some(SigningCurve::Secp256k1),
+        }
+    }
+
+    fn status_of(diagnosis_id: u64) -> DiagnosisStatus {
+        load_diagnosis(diagnosis_id).unwrap().status()
+    }
+
+    /// Mirrors `MedicalDiagnosisResult` as first released, before any optional fields.
+    #[derive(CandidType)]
+    struct LegacyDiagnosis {
+        id: u64,
+        diagnosis: String,
+        confidence_score: f32,
+        medical_findings: Vec<LegacyFinding>,
+        timestamp: u64,
+        signature: Vec<u8>,
+        public_key: Vec<u8>,
+        fda_compliant: bool,
+        hipaa_compliant: bool,
+        model_version: String,
+        patient_metadata: PatientMetadata,
+    }
+
+    #[derive(CandidType)]
+    struct LegacyFinding {
+        finding: String,
+        location: String,
+        severity: String,
+        confidence: f32,
+    }
+
+    #[derive(CandidType)]
+    struct LegacyAuditEntry {
+        id: u64,
+        diagnosis_id: u64,
+        action: String,
+        timestamp: u64,
+        principal_id: Principal,
+        details: String,
+        compliance_flags: Vec<String>,
+    }
+
+    fn legacy_diagnosis(id: u64) -> LegacyDiagnosis {
+        LegacyDiagnosis {
+            id,
+            diagnosis: "Pneumonia detected in right lower lobe".to_string(),
+            confidence_score: 0.87,
+            medical_findings: vec![LegacyFinding {
+                finding: "Consolidation".to_string(),
+                location: "Right lower lobe".to_string(),
+                severity: "Moderate".to_string(),
+                confidence: 0.87,
+            }],
+            timestamp: ic::START_TIME,
+            signature: Vec::new(),
+            public_key: Vec::new(),
+            fda_compliant: true,
+            hipaa_compliant: true,
+            model_version: MODEL_VERSION.to_string(),
+            patient_metadata: metadata("legacy-patient"),
+        }
+    }
+
+    #[test]
+    fn sign_data_round_trips_through_verify_data() {
+        setup();
+        ic::set_caller(provider());
+        let data = b"consent form v3".to_vec();
+
+        let (signature, public_key) = block_on(sign_data(data.clone())).unwrap();
+
+        assert!(verify_data(data, signature, public_key));
+    }
+
+    #[test]
+    fn verify_data_rejects_tampered_payload() {
+        setup();
+        let (signature, public_key) = block_on(sign_data(b"consent form v3".to_vec())).unwrap();
+
+        assert!(!verify_data(b"consent form v4".to_vec(), signature.clone(), public_key.clone()));
+        let mut forged = signature;
+        forged[10] ^= 0x01;
+        assert!(!verify_data(b"consent form v3".to_vec(), forged, public_key));
+    }
+
+    #[test]
+    fn sign_data_is_rate_limited_per_caller() {
+        setup();
+        for _ in 0..MAX_SIGNING_REQUESTS_PER_WINDOW {
+            block_on(sign_data(vec![1])).unwrap();
+        }
+        assert_eq!(block_on(sign_data(vec![1])).unwrap_err(), MedicalError::RateLimited);
+
+        ic::advance_time(SIGNING_RATE_LIMIT_WINDOW_NS);
+        assert!(block_on(sign_data(vec![1])).is_ok());
+    }
+
+    #[test]
+    fn sign_data_requires_admin_or_provider() {
+        setup();
+        ic::set_caller(outsider());
+
+        assert_eq!(block_on(sign_data(vec![1])).unwrap_err(), MedicalError::Unauthorized);
+    }
+
+    #[test]
+    fn bootstrap_admin_only_seeds_an_empty_admin_set() {
+        bootstrap_admin(admin());
+        bootstrap_admin(outsider());
+
+        assert!(is_admin(&admin()));
+        assert!(!is_admin(&outsider()));
+    }
+
+    #[test]
+    fn review_moves_diagnosis_through_under_review() {
+        setup();
+        let diagnosis = analyze(1, "patient-a");
+        assert_eq!(diagnosis.status(), DiagnosisStatus::Active);
+
+        request_review(diagnosis.id, "second read".to_string()).unwrap();
+        assert_eq!(status_of(diagnosis.id), DiagnosisStatus::UnderReview);
+        let queue = get_diagnoses_by_status(DiagnosisStatus::UnderReview, 10);
+        assert_eq!(queue.iter().map(|d| d.id).collect::<Vec<_>>(), vec![diagnosis.id]);
+
+        block_on(submit_review(diagnosis.id, ReviewDecision::Disputed, "unclear".to_string())).unwrap();
+        assert_eq!(status_of(diagnosis.id), DiagnosisStatus::UnderReview);
+
+        block_on(submit_review(diagnosis.id, ReviewDecision::Confirmed, "agree".to_string())).unwrap();
+        assert_eq!(status_of(diagnosis.id), DiagnosisStatus::Active);
+        assert_eq!(
+            block_on(submit_review(diagnosis.id, ReviewDecision::Confirmed, String::new())).unwrap_err(),
+            MedicalError::InvalidStatusTransition(DiagnosisStatus::Active)
+        );
+    }
+
+    #[test]
+    fn amendment_supersedes_the_original() {
+        setup();
+        let original = analyze(1, "patient-a");
+
+        let amended = block_on(amend_diagnosis(original.id, "Normal chest X-ray".to_string(), "misread".to_string())).unwrap();
+
+        assert_eq!(amended.status(), DiagnosisStatus::Amended);
+        assert_eq!(amended.amends, Some(original.id));
+        assert_eq!(status_of(original.id), DiagnosisStatus::Superseded);
+        assert_eq!(
+            block_on(amend_diagnosis(original.id, "again".to_string(), String::new())).err(),
+            Some(MedicalError::InvalidStatusTransition(DiagnosisStatus::Superseded))
+        );
+    }
+
+    #[test]
+    fn deletion_is_terminal() {
+        setup();
+        let diagnosis = analyze(1, "patient-a");
+
+        delete_diagnosis(diagnosis.id).unwrap();
+
+        assert_eq!(status_of(diagnosis.id), DiagnosisStatus::Deleted);
+        assert_eq!(
+            request_review(diagnosis.id, String::new()).unwrap_err(),
+            MedicalError::InvalidStatusTransition(DiagnosisStatus::Deleted)
+        );
+    }
+
+    #[test]
+    fn records_stored_before_optional_fields_still_decode() {
+        let bytes = candid::encode_one(legacy_diagnosis(7)).unwrap();
+        let diagnosis = MedicalDiagnosisResult::from_bytes(Cow::Owned(bytes));
+
+        assert_eq!(diagnosis.status(), DiagnosisStatus::Active);
+        assert_eq!(diagnosis.raw_confidence_score, None);
+        assert_eq!(diagnosis.payload_schema_version(), LEGACY_PAYLOAD_SCHEMA_VERSION);
+        assert_eq!(diagnosis.signing_curve(), SigningCurve::Secp256k1);
+        assert_eq!(diagnosis.medical_findings[0].raw_confidence(), 0.87);
+
+        let entry = MedicalAuditEntry::from_bytes(Cow::Owned(candid::encode_one(LegacyAuditEntry {
+            id: 1,
+            diagnosis_id: 7,
+            action: "DIAGNOSIS_CREATED".to_string(),
+            timestamp: ic::START_TIME,
+            principal_id: admin(),
+            details: String::new(),
+            compliance_flags: Vec::new(),
+        }).unwrap()));
+        assert_eq!(entry.entry_signature, None);
+        assert_eq!(entry.previous_hash, None);
+    }
+
+    fn dated(acquisition_date: &str) -> PatientMetadata {
+        PatientMetadata {
+            acquisition_date: acquisition_date.to_string(),
+            ..metadata("patient-a")
+        }
+    }
+
+    #[test]
+    fn acquisition_dates_are_checked_against_the_clock() {
+        let config = CanisterConfig {
+            reject_future_acquisition_dates: true,
+            ..CanisterConfig::default()
+        };
+        // 2025-06-15T00:00:00Z
+        let now = 1_749_945_600_000_000_000;
+
+        assert_eq!(validate_acquisition_date(&dated("2024-03-01"), &config, now), Ok(()));
+        assert_eq!(validate_acquisition_date(&dated("2025-06-15"), &config, now), Ok(()));
+        // Within the one-day skew allowed for sites ahead of UTC
+        assert_eq!(validate_acquisition_date(&dated("2025-06-16"), &config, now), Ok(()));
+        assert_eq!(
+            validate_acquisition_date(&dated("2025-06-17"), &config, now),
+            Err(MedicalError::InvalidAcquisitionDate)
+        );
+        assert_eq!(
+            validate_acquisition_date(&dated("not a date"), &config, now),
+            Err(MedicalError::InvalidAcquisitionDate)
+        );
+    }
+
+    #[test]
+    fn acquisition_days_are_checked_against_the_month_length() {
+        assert!(parse_acquisition_date("2024-02-29").is_some());
+        assert!(parse_acquisition_date("2000-02-29").is_some());
+        assert!(parse_acquisition_date("2023-04-30").is_some());
+        assert_eq!(parse_acquisition_date("2024-02-30"), None);
+        assert_eq!(parse_acquisition_date("2024-02-31"), None);
+        assert_eq!(parse_acquisition_date("2023-02-29"), None);
+        assert_eq!(parse_acquisition_date("2100-02-29"), None);
+        assert_eq!(parse_acquisition_date("2023-04-31"), None);
+        // Past the last nanosecond timestamp a u64 can hold (2554)
+        assert_eq!(parse_acquisition_date("9999-01-01"), None);
+    }
+
+    #[test]
+    fn future_dated_analysis_is_rejected_when_enabled() {
+        setup();
+        let future = dated("2031-01-01");
+        assert!(block_on(analyze_medical_image(image(1), future.clone())).is_ok());
+
+        configure(|config| config.reject_future_acquisition_dates = true);
+        assert_eq!(
+            block_on(analyze_medical_image(image(2), future)).err(),
+            Some(MedicalError::InvalidAcquisitionDate)
+        );
+        assert!(block_on(analyze_medical_image(image(3), dated("2025-06-01"))).is_ok());
+    }
+
+    #[test]
+    fn signature_batches_cover_the_dataset_in_windows() {
+        setup();
+        let ids: Vec<u64> = (1..=5).map(|seed| analyze(seed, "patient-a").id).collect();
+        let mut tampered = load_diagnosis(ids[3]).unwrap();
+        tampered.diagnosis.push_str(" - edited");
+        store_diagnosis(&tampered);
+
+        let (mut verified, mut failed, mut failed_ids) = (0, 0, Vec::new());
+        let mut cursor = Some(ids[0]);
+        let mut windows = 0;
+        while let Some(start_id) = cursor {
+            let batch = verify_signatures_batch(start_id, 2);
+            verified += batch.verified;
+            failed += batch.failed;
+            failed_ids.extend(batch.failed_ids);
+            cursor = batch.next_cursor;
+            windows += 1;
+        }
+
+        assert_eq!(windows, 3);
+        assert_eq!((verified, failed), (4, 1));
+        assert_eq!(failed_ids, vec![ids[3]]);
+    }
+
+    #[test]
+    fn archived_records_stay_verifiable() {
+        setup();
+        let ids: Vec<u64> = (1..=4).map(|seed| analyze(seed, "patient-a").id).collect();
+        for id in [ids[1], ids[3]] {
+            let archived = DIAGNOSES.with(|diagnoses| diagnoses.borrow_mut().remove(&id)).unwrap();
+            ARCHIVED_DIAGNOSES.with(|archive| archive.borrow_mut().insert(id, archived));
+        }
+
+        let first = verify_signatures_batch(ids[0], 3);
+        assert_eq!((first.verified, first.failed, first.next_cursor), (3, 0, Some(ids[3])));
+        let second = verify_signatures_batch(ids[3], 3);
+        assert_eq!((second.verified, second.failed, second.next_cursor), (1, 0, None));
+
+        let (verified, _) = block_on(attest_verification(ids[1])).unwrap();
+        assert!(verified);
+    }
+
+    #[test]
+    fn import_preserves_signature_and_provenance() {
+        setup();
+        let exported = analyze(1, "patient-a");
+
+        let imported_id = import_diagnosis(exported.clone()).unwrap();
+
+        assert_ne!(imported_id, exported.id);
+        let imported = load_diagnosis(imported_id).unwrap();
+        assert_eq!(imported.source_id, Some(exported.id));
+        assert_eq!(imported.signature, exported.signature);
+        assert_eq!(verify_diagnosis_signature(imported_id), Ok(true));
+        let actions: Vec<String> = get_audit_trail_for_diagnosis(imported_id).into_iter().map(|e| e.action).collect();
+        assert_eq!(actions, vec!["DIAGNOSIS_IMPORTED"]);
+
+        // Re-importing keeps the original source ID rather than the intermediate one
+        let reimported = import_diagnosis(imported).unwrap();
+        assert_eq!(load_diagnosis(reimported).unwrap().source_id, Some(exported.id));
+    }
+
+    #[test]
+    fn import_rejects_records_failing_verification() {
+        setup();
+        let mut forged = analyze(1, "patient-a");
+        forged.confidence_score = 0.99;
+        let count = DIAGNOSES.with(|diagnoses| diagnoses.borrow().len());
+
+        assert_eq!(import_diagnosis(forged.clone()), Err(MedicalError::InvalidSignature));
+        assert_eq!(DIAGNOSES.with(|diagnoses| diagnoses.borrow().len()), count);
+
+        ic::set_caller(provider());
+        assert_eq!(import_diagnosis(forged), Err(MedicalError::Unauthorized));
+    }
+
+    #[test]
+    fn import_drops_source_links_and_rejects_retired_records() {
+        setup();
+        let exported = analyze(1, "patient-a");
+
+        let mut linked = exported.clone();
+        linked.status = Some(DiagnosisStatus::Amended);
+        linked.amends = Some(exported.id + 100);
+        linked.review = Some(ReviewRecord {
+            reviewer: admin(),
+            decision: ReviewDecision::Confirmed,
+            notes: "Agreed".to_string(),
+            timestamp: ic::START_TIME,
+            review_signature: Vec::new(),
+            review_public_key: Vec::new(),
+        });
+        let imported = load_diagnosis(import_diagnosis(linked).unwrap()).unwrap();
+        assert_eq!(imported.amends, None);
+        assert!(imported.review.is_none());
+
+        for status in [DiagnosisStatus::Superseded, DiagnosisStatus::Deleted] {
+            let mut retired = exported.clone();
+            retired.status = Some(status);
+            assert_eq!(import_diagnosis(retired), Err(MedicalError::InvalidStatusTransition(status)));
+        }
+    }
+
+    #[test]
+    fn import_validates_patient_metadata() {
+        setup();
+        let exported = analyze(1, "patient-a");
+        configure(|c| {
+            c.reject_future_acquisition_dates = true;
+            c.accepted_date_formats = vec![DateFormat::IsoDate, DateFormat::DicomDate];
+        });
+
+        let mut future = exported.clone();
+        future.patient_metadata.acquisition_date = "9999-01-01".to_string();
+        assert_eq!(import_diagnosis(future), Err(MedicalError::InvalidAcquisitionDate));
+
+        let mut malformed = exported.clone();
+        malformed.patient_metadata.acquisition_date = "15/01/2024".to_string();
+        assert_eq!(import_diagnosis(malformed), Err(MedicalError::InvalidDateFormat));
+
+        let mut dicom = exported;
+        dicom.patient_metadata.acquisition_date = "20240115".to_string();
+        let imported = load_diagnosis(import_diagnosis(dicom).unwrap()).unwrap();
+        assert_eq!(imported.patient_metadata.acquisition_date, "2024-01-15");
+    }
+
+    #[test]
+    fn confidence_floor_filters_query_output_only() {
+        setup();
+        let mut diagnosis = record(1, "patient-a");
+        diagnosis.medical_findings = vec![
+            finding("Consolidation", "Moderate", 0.87),
+            finding("Air bronchograms", "Mild", 0.40),
+        ];
+        store_diagnosis(&diagnosis);
+        configure(|config| config.output_finding_confidence_floor = Some(0.5));
+
+        let filtered = get_diagnosis(1, None).unwrap();
+        assert_eq!(filtered.medical_findings.len(), 1);
+        assert_eq!(filtered.medical_findings[0].finding, "Consolidation");
+        assert_eq!(get_all_diagnoses()[0].medical_findings.len(), 1);
+
+        assert_eq!(get_diagnosis(1, Some(true)).unwrap().medical_findings.len(), 2);
+        assert_eq!(load_diagnosis(1).unwrap().medical_findings.len(), 2);
+    }
+
+    #[test]
+    fn metadata_conflicts_report_inconsistent_age_ranges() {
+        for (id, anonymized_id, age_range) in [
+            (1, "consistent", "40-49"),
+            (2, "consistent", "40-49"),
+            (3, "conflicting", "40-49"),
+            (4, "conflicting", "60-69"),
+            (5, "conflicting", "40-49"),
+        ] {
+            let mut diagnosis = record(id, anonymized_id);
+            diagnosis.patient_metadata.age_range = age_range.to_string();
+            store_diagnosis(&diagnosis);
+        }
+
+        assert_eq!(find_metadata_conflicts(), vec![("conflicting".to_string(), vec![3, 4, 5])]);
+    }
+
+    #[test]
+    fn compliance_report_batch_returns_per_item_outcomes() {
+        setup();
+        store_diagnosis(&record(1, "patient-a"));
+        store_diagnosis(&record(2, "patient-b"));
+
+        let reports = get_compliance_reports_batch(vec![1, 99, 2]);
+
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[0].as_ref().map(|r| r.diagnosis_id).ok(), Some(1));
+        assert_eq!(reports[1].as_ref().err(), Some(&MedicalError::DiagnosisNotFound));
+        assert_eq!(reports[2].as_ref().map(|r| r.diagnosis_id).ok(), Some(2));
+    }
+
+    #[test]
+    fn compliance_report_batch_is_capped() {
+        setup();
+        store_diagnosis(&record(1, "patient-a"));
+
+        let entries = audit_trail_len();
+        let errors = RECENT_ERRORS.with(|errors| errors.borrow().len());
+
+        let reports = get_compliance_reports_batch(vec![1; MAX_COMPLIANCE_BATCH_SIZE as usize + 1]);
+
+        let too_large = MedicalError::BatchTooLarge(MAX_COMPLIANCE_BATCH_SIZE);
+        assert!(reports.iter().all(|report| report.as_ref().err() == Some(&too_large)));
+        assert_eq!(audit_trail_len(), entries);
+        assert_eq!(RECENT_ERRORS.with(|errors| errors.borrow().len()), errors + 1);
+
+        let reports = get_compliance_reports_batch(vec![1; MAX_COMPLIANCE_BATCH_SIZE as usize]);
+        assert!(reports.iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn calibration_transforms_confidence_predictably() {
+        let identity = ConfidenceCalibration { slope: 1.0, intercept: 0.0 };
+        let temperature_two = ConfidenceCalibration { slope: 0.5, intercept: 0.0 };
+
+        assert!((calibrate_confidence(0.9, &identity) - 0.9).abs() < 1e-6);
+        // sigmoid(ln(9) / 2) = 3 / 4
+        assert!((calibrate_confidence(0.9, &temperature_two) - 0.75).abs() < 1e-6);
+        assert!((calibrate_confidence(0.5, &temperature_two) - 0.5).abs() < 1e-6);
+        assert!(calibrate_confidence(1.0, &identity).is_finite());
+        assert!(calibrate_confidence(0.0, &identity) >= 0.0);
+    }
+
+    #[test]
+    fn calibration_preserves_raw_confidences() {
+        setup();
+        let calibration = ConfidenceCalibration { slope: 0.5, intercept: 0.0 };
+        configure(|config| config.confidence_calibration = Some(calibration));
+
+        let diagnosis = analyze(1, "patient-a");
+
+        let raw = diagnosis.raw_confidence_score.unwrap();
+        assert_eq!(diagnosis.confidence_score, calibrate_confidence(raw, &calibration));
+        assert!(diagnosis.confidence_score < raw);
+        for finding in &diagnosis.medical_findings {
+            assert_eq!(finding.confidence, calibrate_confidence(finding.raw_confidence(), &calibration));
+            assert_ne!(finding.confidence, finding.raw_confidence());
+        }
+    }
+
+    fn audit_trail_len() -> u64 {
+        AUDIT_TRAIL.with(|trail| trail.borrow().len())
+    }
+
+    #[test]
+    fn anonymous_callers_are_rejected_before_auditing() {
+        setup();
+        store_diagnosis(&record(1, "patient-a"));
+        ic::set_caller(Principal::anonymous());
+
+        assert_eq!(
+            block_on(analyze_medical_image(image(1), metadata("patient-a"))).err(),
+            Some(MedicalError::AnonymousCaller)
+        );
+        assert!(get_fda_compliance_report(1).is_err());
+        assert_eq!(get_compliance_reports_batch(vec![1])[0].as_ref().err(), Some(&MedicalError::AnonymousCaller));
+        assert_eq!(request_review(1, String::new()), Err(MedicalError::Unauthorized));
+        assert_eq!(delete_diagnosis(1), Err(MedicalError::Unauthorized));
+        assert_eq!(block_on(sign_data(vec![1])).unwrap_err(), MedicalError::Unauthorized);
+        assert_eq!(block_on(get_negative_attestation(1)).unwrap_err(), MedicalError::AnonymousCaller);
+        assert_eq!(begin_upload(), Err(MedicalError::AnonymousCaller));
+
+        assert_eq!(audit_trail_len(), 0);
+    }
+
+    #[test]
+    fn audit_entries_attributed_to_anonymous_are_flagged() {
+        ic::set_caller(Principal::anonymous());
+        add_audit_entry(1, "TEST".to_string(), String::new());
+        ic::set_caller(admin());
+        add_audit_entry(1, "TEST".to_string(), String::new());
+
+        let trail = get_medical_audit_trail();
+        assert!(trail[0].compliance_flags.contains(&"ANONYMOUS_CALLER".to_string()));
+        assert!(!trail[1].compliance_flags.contains(&"ANONYMOUS_CALLER".to_string()));
+    }
+
+    #[test]
+    fn config_changes_record_before_and_after() {
+        setup();
+        let original = config();
+        let updated = CanisterConfig {
+            dedup_ttl_ns: 0,
+            ..original.clone()
+        };
+
+        update_config(updated).unwrap();
+        ic::advance_time(1_000);
+        update_config(CanisterConfig {
+            access_logging_enabled: false,
+            ..config()
+        })
+        .unwrap();
+
+        let history = get_config_history(10);
+        assert_eq!(history.iter().map(|change| change.id).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(history[1].admin, admin());
+        assert_eq!(history[1].timestamp, ic::START_TIME);
+        assert_eq!(history[1].old_config.dedup_ttl_ns, original.dedup_ttl_ns);
+        assert_eq!(history[1].new_config.dedup_ttl_ns, 0);
+        assert!(history[0].old_config.access_logging_enabled);
+        assert!(!history[0].new_config.access_logging_enabled);
+        assert_eq!(get_config_history(1).len(), 1);
+    }
+
+    #[test]
+    fn config_changes_require_admin() {
+        setup();
+        ic::set_caller(provider());
+
+        assert_eq!(update_config(config()), Err(MedicalError::Unauthorized));
+        assert!(get_config_history(10).is_empty());
+    }
+
+    #[test]
+    fn self_audit_passes_a_fully_configured_canister() {
+        setup();
+        register_model(MODEL_VERSION.to_string(), "chest X-ray".to_string(), true, true).unwrap();
+        configure(|config| {
+            config.ecdsa_key_name = "key_1".to_string();
+            config.retention_period_days = Some(3650);
+        });
+
+        let audit = run_compliance_self_audit();
+
+        assert!(audit.passed);
+        assert!(audit.checks.iter().all(|check| check.passed && check.remediation.is_none()));
+    }
+
+    #[test]
+    fn self_audit_reports_each_misconfiguration() {
+        setup();
+        register_model(MODEL_VERSION.to_string(), "chest X-ray".to_string(), false, true).unwrap();
+        configure(|config| config.access_logging_enabled = false);
+
+        let audit = run_compliance_self_audit();
+
+        assert!(!audit.passed);
+        let failed: Vec<&str> = audit.checks.iter().filter(|check| !check.passed).map(|check| check.name.as_str()).collect();
+        assert_eq!(failed, vec!["FDA_CLEARED_MODEL_ACTIVE", "PRODUCTION_SIGNING_KEY", "RETENTION_POLICY", "ACCESS_LOGGING"]);
+        assert!(audit.checks.iter().all(|check| check.passed == check.remediation.is_none()));
+    }
+
+    #[test]
+    fn review_signature_attributes_the_decision() {
+        setup();
+        let diagnosis = analyze(1, "patient-a");
+        request_review(diagnosis.id, "second read".to_string()).unwrap();
+        ic::set_caller(provider());
+
+        block_on(submit_review(diagnosis.id, ReviewDecision::Confirmed, "agree".to_string())).unwrap();
+
+        let review = load_diagnosis(diagnosis.id).unwrap().review.unwrap();
+        assert_eq!(review.reviewer, provider());
+        assert_eq!(verify_review(diagnosis.id), Ok(true));
+
+        let mut tampered = load_diagnosis(diagnosis.id).unwrap();
+        tampered.review.as_mut().unwrap().decision = ReviewDecision::Disputed;
+        store_diagnosis(&tampered);
+        assert_eq!(verify_review(diagnosis.id), Ok(false));
+    }
+
+    #[test]
+    fn verify_review_requires_a_review() {
+        store_diagnosis(&record(1, "patient-a"));
+
+        assert_eq!(verify_review(1), Err(MedicalError::ReviewNotFound));
+        assert_eq!(verify_review(2), Err(MedicalError::DiagnosisNotFound));
+    }
+
+    #[test]
+    fn image_size_is_reported_to_the_byte() {
+        for len in [1024, 1025, 2047, 5000, 1024 * 1024 + 1] {
+            let metrics = validate_medical_image(&vec![0; len]).unwrap();
+            assert_eq!(metrics.image_size_bytes, len as u64);
+            assert_eq!(metrics.image_size_kb, (len / 1024) as u32);
+        }
+        assert!(validate_medical_image(&[0; 1023]).is_err());
+    }
+
+    #[test]
+    fn analysis_reports_exact_image_size() {
+        setup();
+
+        let diagnosis = block_on(analyze_medical_image(vec![7; 3001], metadata("patient-a"))).unwrap();
+
+        let metrics = diagnosis.metrics.unwrap();
+        assert_eq!(metrics.image_size_bytes, 3001);
+        assert_eq!(metrics.image_size_kb, 2);
+    }
+
+    fn consent(anonymized_id: &str) -> ConsentRecord {
+        ConsentRecord {
+            anonymized_id: anonymized_id.to_string(),
+            consent_hash: "ab".repeat(32),
+            scopes: vec![ConsentScope::Diagnosis],
+            granted_at: ic::START_TIME,
+        }
+    }
+
+    #[test]
+    fn noncompliant_diagnoses_report_failing_criteria() {
+        setup();
+        register_model(MODEL_VERSION.to_string(), "chest X-ray".to_string(), true, true).unwrap();
+        for anonymized_id in ["compliant", "inactive-model"] {
+            record_consent(consent(anonymized_id)).unwrap();
+        }
+        let signed = |id, anonymized_id: &str| MedicalDiagnosisResult {
+            signature: vec![1; 64],
+            ..record(id, anonymized_id)
+        };
+        store_diagnosis(&signed(1, "compliant"));
+        store_diagnosis(&signed(2, "no-consent"));
+        store_diagnosis(&MedicalDiagnosisResult {
+            model_version: "retired-model".to_string(),
+            ..signed(3, "inactive-model")
+        });
+        store_diagnosis(&signed(4, " "));
+
+        let noncompliant: BTreeMap<u64, Vec<String>> = get_noncompliant_diagnoses(10).into_iter().collect();
+
+        assert!(!noncompliant.contains_key(&1));
+        let reasons = |id: u64| -> Vec<&str> {
+            noncompliant[&id].iter().map(|reason| reason.split(':').next().unwrap()).collect()
+        };
+        assert_eq!(reasons(2), vec!["MISSING_CONSENT"]);
+        assert_eq!(reasons(3), vec!["INACTIVE_MODEL"]);
+        assert_eq!(reasons(4), vec!["MISSING_CONSENT", "DEIDENTIFICATION_FAILED"]);
+        assert_eq!(get_noncompliant_diagnoses(1).len(), 1);
+    }
+
+    #[test]
+    fn consent_batch_validates_each_record() {
+        setup();
+        let records = vec![
+            consent("patient-a"),
+            ConsentRecord { consent_hash: "not-hex".to_string(), ..consent("patient-b") },
+            ConsentRecord { scopes: Vec::new(), ..consent("patient-c") },
+            consent(""),
+            consent("patient-d"),
+        ];
+
+        let results = record_consent_batch(records);
+
+        assert_eq!(results[0], Ok(()));
+        assert!(matches!(results[1], Err(MedicalError::InvalidConsent(_))));
+        assert!(matches!(results[2], Err(MedicalError::InvalidConsent(_))));
+        assert!(matches!(results[3], Err(MedicalError::InvalidConsent(_))));
+        assert_eq!(results[4], Ok(()));
+        assert!(get_consent("patient-a".to_string()).is_some());
+        assert!(get_consent("patient-b".to_string()).is_none());
+        assert!(get_consent("patient-d".to_string()).is_some());
+    }
+
+    #[test]
+    fn consent_batch_is_capped() {
+        setup();
+        let records = (0..=MAX_CONSENT_BATCH_SIZE).map(|i| consent(&format!("patient-{}", i))).collect();
+
+        let results = record_consent_batch(records);
+
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count() as u64, MAX_CONSENT_BATCH_SIZE);
+        assert_eq!(results.last().unwrap(), &Err(MedicalError::BatchTooLarge(MAX_CONSENT_BATCH_SIZE)));
+    }
+
+    #[test]
+    fn signed_audit_entries_verify_in_isolation() {
+        setup();
+        configure(|config| config.sign_audit_entries = true);
+        add_audit_entry(1, "DIAGNOSIS_CREATED".to_string(), "analyzed".to_string());
+        assert_eq!(verify_audit_entry(1), Ok(false));
+
+        ic::run_spawned();
+
+        assert_eq!(verify_audit_entry(1), Ok(true));
+        let mut tampered = AUDIT_TRAIL.with(|trail| trail.borrow().get(&1)).unwrap();
+        tampered.details = "edited".to_string();
+        AUDIT_TRAIL.with(|trail| trail.borrow_mut().insert(1, tampered));
+        assert_eq!(verify_audit_entry(1), Ok(false));
+        assert_eq!(verify_audit_entry(2), Err(MedicalError::AuditEntryNotFound));
+    }
+
+    #[test]
+    fn audit_entries_stay_unsigned_unless_enabled() {
+        setup();
+        add_audit_entry(1, "DIAGNOSIS_CREATED".to_string(), "analyzed".to_string());
+        ic::run_spawned();
+
+        assert_eq!(get_medical_audit_trail()[0].entry_signature, None);
+        assert_eq!(verify_audit_entry(1), Ok(false));
+    }
+
+    #[test]
+    fn storage_stats_average_encoded_sizes() {
+        setup();
+        let records: Vec<MedicalDiagnosisResult> = (1..=3).map(|id| record(id, "patient-a")).collect();
+        records.iter().for_each(store_diagnosis);
+        for id in 1..=4 {
+            add_audit_entry(id, "DIAGNOSIS_CREATED".to_string(), "analyzed".to_string());
+        }
+
+        let stats = get_storage_stats();
+
+        let expected_total: u64 = records.iter().map(|d| d.to_bytes().len() as u64).sum();
+        assert_eq!(stats.diagnoses.count, 3);
+        assert_eq!(stats.diagnoses.total_bytes, expected_total);
+        assert_eq!(stats.diagnoses.average_bytes, expected_total / 3);
+        assert!((500..MAX_DIAGNOSIS_BYTES as u64).contains(&stats.diagnoses.average_bytes));
+        assert_eq!(stats.audit_entries.count, 4);
+        assert!((100..4096).contains(&stats.audit_entries.average_bytes));
+    }
+
+    #[test]
+    fn storage_stats_are_zero_when_empty() {
+        let stats = get_storage_stats();
+
+        assert_eq!((stats.diagnoses.count, stats.diagnoses.average_bytes), (0, 0));
+        assert_eq!((stats.audit_entries.count, stats.audit_entries.average_bytes), (0, 0));
+    }
+
+    #[test]
+    fn contradictory_findings_are_detected() {
+        let normal = "Normal chest X-ray - No acute cardiopulmonary process";
+        let pneumonia = "Pneumonia detected in right lower lobe";
+
+        assert_eq!(findings_contradiction(normal, &[finding("Clear lung fields", "Normal", 0.9)]), None);
+        assert_eq!(findings_contradiction(pneumonia, &[finding("Consolidation", "Moderate", 0.9)]), None);
+        assert_eq!(findings_contradiction(pneumonia, &[]), None);
+
+        assert_eq!(
+            findings_contradiction(normal, &[finding("Clear lung fields", "Normal", 0.9), finding("Mass", "Severe", 0.8)]),
+            Some("Normal diagnosis carries Severe finding 'Mass'".to_string())
+        );
+        assert!(findings_contradiction(pneumonia, &[finding("Clear lung fields", "normal", 0.9)]).is_some());
+    }
+
+    #[test]
+    fn consistent_results_pass_the_strict_policy() {
+        setup();
+        configure(|config| config.findings_consistency_policy = FindingsConsistencyPolicy::Reject);
+
+        let diagnosis = analyze(1, "patient-a");
+
+        assert_eq!(diagnosis.status(), DiagnosisStatus::Active);
+        assert!(get_audit_trail_for_diagnosis(diagnosis.id).iter().all(|e| e.action != "INCONSISTENT_FINDINGS_FLAGGED"));
+    }
+
+    #[test]
+    fn storage_runway_extrapolates_recent_growth() {
+        let used_bytes = 10 * 1024 * 1024 * 1024;
+        configure(|config| config.storage_capacity_bytes = used_bytes + 1024 * 1024);
+        let now = ic::START_TIME;
+        let mut recent_bytes = 0;
+        for day in 1..=10 {
+            let diagnosis = MedicalDiagnosisResult {
+                timestamp: now - day * NANOS_PER_DAY,
+                ..record(day, "patient-a")
+            };
+            recent_bytes += diagnosis.to_bytes().len() as u64;
+            store_diagnosis(&diagnosis);
+        }
+        // Outside the growth window
+        store_diagnosis(&MedicalDiagnosisResult {
+            timestamp: now - (STORAGE_GROWTH_WINDOW_DAYS + 5) * NANOS_PER_DAY,
+            ..record(99, "patient-a")
+        });
+
+        let runway = estimate_storage_runway();
+
+        assert_eq!(runway.used_bytes, used_bytes);
+        assert_eq!(runway.growth_bytes_per_day, recent_bytes / 10);
+        let days = (runway.capacity_bytes - runway.used_bytes) / runway.growth_bytes_per_day;
+        assert_eq!(runway.estimated_days_to_full, Some(days));
+        assert_eq!(runway.estimated_full_timestamp, Some(now + days * NANOS_PER_DAY));
+    }
+
+    #[test]
+    fn storage_runway_is_open_ended_without_growth() {
+        let runway = estimate_storage_runway();
+
+        assert_eq!(runway.growth_bytes_per_day, 0);
+        assert_eq!(runway.estimated_days_to_full, None);
+        assert_eq!(runway.estimated_full_timestamp, None);
+    }
+
+    #[test]
+    fn diagnoses_filter_by_model_version() {
+        for (id, version) in [(1, "v1"), (2, "v2"), (3, "v1"), (4, "v3"), (5, "v1")] {
+            store_diagnosis(&MedicalDiagnosisResult {
+                model_version: version.to_string(),
+                ..record(id, "patient-a")
+            });
+        }
+
+        let ids = |version: &str, limit| -> Vec<u64> {
+            get_diagnoses_by_model(version.to_string(), limit).into_iter().map(|d| d.id).collect()
+        };
+        assert_eq!(ids("v1", 10), vec![1, 3, 5]);
+        assert_eq!(ids("v1", 2), vec![1, 3]);
+        assert_eq!(ids("v2", 10), vec![2]);
+        assert!(ids("v9", 10).is_empty());
+    }
+
+    #[test]
+    fn resubmitted_image_is_deduplicated_within_window() {
+        setup();
+        let first = analyze(7, "patient-a");
+        ic::advance_time(config().dedup_ttl_ns - 1);
+
+        assert_eq!(analyze(7, "patient-a").id, first.id);
+        assert_ne!(analyze(7, "patient-b").id, first.id);
+        assert_ne!(analyze(8, "patient-a").id, first.id);
+    }
+
+    #[test]
+    fn resubmitted_image_is_reanalyzed_after_window() {
+        setup();
+        let first = analyze(7, "patient-a");
+        ic::advance_time(config().dedup_ttl_ns);
+
+        let second = analyze(7, "patient-a");
+        assert_ne!(second.id, first.id);
+        assert_eq!(analyze(7, "patient-a").id, second.id);
+    }
+
+    #[test]
+    fn expired_dedup_entries_are_swept() {
+        setup();
+        for seed in 0..20 {
+            analyze(seed, "patient-a");
+        }
+        ic::advance_time(config().dedup_ttl_ns);
+        let fresh = analyze(100, "patient-a");
+        let remaining = IMAGE_DEDUP.with(|dedup| dedup.borrow().len());
+        assert!(remaining < 21, "insert should sweep expired entries, {remaining} remain");
+
+        // The first pass resumes from the sweep cursor and wraps, the second covers the whole map
+        let pruned = prune_dedup_entries(1000).unwrap() + prune_dedup_entries(1000).unwrap();
+        assert_eq!(pruned, remaining - 1);
+        let keys: Vec<String> = IMAGE_DEDUP.with(|dedup| dedup.borrow().iter().map(|(key, _)| key).collect());
+        assert_eq!(keys, vec![dedup_key("patient-a", &image(100))]);
+        assert_eq!(analyze(100, "patient-a").id, fresh.id);
+
+        ic::set_caller(provider());
+        assert_eq!(prune_dedup_entries(1000), Err(MedicalError::Unauthorized));
+    }
+
+    #[test]
+    fn candid_interface_lists_endpoints() {
+        let interface = get_candid_interface();
+        assert!(interface.contains("service"));
+        for method in ["analyze_medical_image", "get_candid_interface", "verify_audit_chain", "prune_dedup_entries"] {
+            assert!(interface.contains(method), "{method} missing from the interface");
+        }
+        assert!(interface.contains("type MedicalError"));
+    }
+
+    #[test]
+    fn anonymized_id_entropy_estimates() {
+        assert_eq!(anonymized_id_entropy_bits(""), 0.0);
+        assert_eq!(anonymized_id_entropy_bits("aaaa"), 0.0);
+        assert!((anonymized_id_entropy_bits("abcd") - 8.0).abs() < 1e-4);
+
+        let weak = anonymized_id_entropy_bits("patient1");
+        assert!((21.0..23.0).contains(&weak), "patient1 scored {weak}");
+        let strong = anonymized_id_entropy_bits("3f9a7c1e5b2d8046af13c97e2b5d0864");
+        assert!(strong > 100.0, "random hex scored {strong}");
+    }
+
+    #[test]
+    fn weak_anonymized_ids_are_rejected() {
+        setup();
+        configure(|c| c.anonymization_policy = Some(AnonymizationPolicy { min_length: 16, min_entropy_bits: 64.0 }));
+
+        let analyze_as = |anonymized_id: &str| block_on(analyze_medical_image(image(1), metadata(anonymized_id))).err();
+        assert_eq!(analyze_as("patient1"), Some(MedicalError::WeakAnonymization));
+        assert_eq!(analyze_as("aaaaaaaaaaaaaaaaaaaaaaaa"), Some(MedicalError::WeakAnonymization));
+        assert_eq!(analyze_as("3f9a7c1e5b2d8046af13c97e2b5d0864"), None);
+    }
+
+    #[test]
+    fn daily_counts_bucket_by_utc_day() {
+        let day = ic::START_TIME - ic::START_TIME % NANOS_PER_DAY;
+        let timestamps = [day, day + 1, day + NANOS_PER_DAY - 1, day + 2 * NANOS_PER_DAY + 5, day + 3 * NANOS_PER_DAY];
+        for (id, timestamp) in (1..).zip(timestamps) {
+            store_diagnosis(&MedicalDiagnosisResult { timestamp, ..record(id, "patient-a") });
+        }
+
+        assert_eq!(
+            get_daily_analysis_counts(day, day + 3 * NANOS_PER_DAY),
+            vec![(day, 3), (day + 2 * NANOS_PER_DAY, 1)],
+        );
+        assert_eq!(get_daily_analysis_counts(day + 1, day + NANOS_PER_DAY), vec![(day, 2)]);
+        assert!(get_daily_analysis_counts(day + NANOS_PER_DAY, day + 2 * NANOS_PER_DAY).is_empty());
+    }
+
+    #[test]
+    fn analysis_requires_registered_active_model() {
+        setup();
+        configure(|c| c.enforce_registered_model = true);
+        let attempt = || block_on(analyze_medical_image(image(1), metadata("patient-a"))).err();
+        let unregistered = Some(MedicalError::UnregisteredModel(MODEL_VERSION.to_string()));
+
+        assert_eq!(attempt(), unregistered);
+        register_model(MODEL_VERSION.to_string(), "Chest X-ray".to_string(), true, false).unwrap();
+        assert_eq!(attempt(), unregistered);
+        register_model(MODEL_VERSION.to_string(), "Chest X-ray".to_string(), true, true).unwrap();
+        assert_eq!(attempt(), None);
+
+        configure(|c| c.enforce_registered_model = false);
+        register_model(MODEL_VERSION.to_string(), "Chest X-ray".to_string(), true, false).unwrap();
+        assert_eq!(attempt(), None);
+    }
+
+    #[test]
+    fn model_registration_requires_admin() {
+        setup();
+        ic::set_caller(provider());
+        assert_eq!(
+            register_model("MedicalAI-v3.0.0".to_string(), String::new(), false, true),
+            Err(MedicalError::Unauthorized),
+        );
+        assert!(get_registered_models().is_empty());
+    }
+
+    #[test]
+    fn negative_attestation_signs_normal_results() {
+        setup();
+        store_diagnosis(&MedicalDiagnosisResult {
+            diagnosis: "Normal chest radiograph".to_string(),
+            medical_findings: vec![finding("Clear lung fields", "Normal", 0.95)],
+            ..record(1, "patient-a")
+        });
+
+        let (statement, signature) = block_on(get_negative_attestation(1)).unwrap();
+        assert!(statement.starts_with("NEGATIVE ATTESTATION: Diagnosis 1 for patient patient-a"));
+        let public_key = ic::public_key(&ecdsa_key_id(&config()).unwrap(), &[]);
+        assert!(verify_data(statement.into_bytes(), signature, public_key));
+        assert!(get_audit_trail_for_diagnosis(1).iter().any(|entry| entry.action == "NEGATIVE_ATTESTATION_ISSUED"));
+    }
+
+    #[test]
+    fn negative_attestation_rejects_abnormal_or_inactive_results() {
+        setup();
+        store_diagnosis(&record(1, "patient-a"));
+        store_diagnosis(&MedicalDiagnosisResult {
+            diagnosis: "Normal chest radiograph".to_string(),
+            status: Some(DiagnosisStatus::Superseded),
+            ..record(2, "patient-a")
+        });
+        store_diagnosis(&MedicalDiagnosisResult {
+            diagnosis: "Normal chest radiograph".to_string(),
+            medical_findings: vec![finding("Mass", "Severe", 0.91)],
+            ..record(3, "patient-a")
+        });
+        store_diagnosis(&MedicalDiagnosisResult {
+            diagnosis: "Normal chest radiograph".to_string(),
+            medical_findings: vec![finding("Clear lung fields", "Normal", 0.95)],
+            status: Some(DiagnosisStatus::UnderReview),
+            ..record(4, "patient-a")
+        });
+
+        assert_eq!(block_on(get_negative_attestation(1)).err(), Some(MedicalError::NotNormalDiagnosis));
+        assert_eq!(
+            block_on(get_negative_attestation(2)).err(),
+            Some(MedicalError::InvalidStatusTransition(DiagnosisStatus::Superseded)),
+        );
+        assert_eq!(block_on(get_negative_attestation(3)).err(), Some(MedicalError::NotNormalDiagnosis));
+        assert_eq!(
+            block_on(get_negative_attestation(4)).err(),
+            Some(MedicalError::InvalidStatusTransition(DiagnosisStatus::UnderReview)),
+        );
+        assert_eq!(audit_trail_len(), 0);
+    }
+
+    #[test]
+    fn compressed_and_plain_records_coexist() {
+        let plain = record(1, "patient-a");
+        store_diagnosis(&plain);
+        let plain_bytes = plain.to_bytes().into_owned();
+        assert!(plain_bytes.starts_with(CANDID_MAGIC));
+
+        configure(|c| c.compress_diagnoses = true);
+        let compressed = record(2, "patient-a");
+        store_diagnosis(&compressed);
+        let compressed_bytes = compressed.to_bytes().into_owned();
+        assert_eq!(compressed_bytes[0], DIAGNOSIS_FORMAT_DEFLATE);
+        assert!(compressed_bytes.len() < plain_bytes.len());
+
+        let encoded = |diagnosis: &MedicalDiagnosisResult| candid::encode_one(diagnosis).unwrap();
+        for original in [plain, compressed] {
+            let stored = DIAGNOSES.with(|diagnoses| diagnoses.borrow().get(&original.id)).unwrap();
+            assert_eq!(encoded(&stored), encoded(&original));
+        }
+        let decoded = MedicalDiagnosisResult::from_bytes(Cow::Owned(plain_bytes));
+        assert_eq!(decoded.id, 1);
+    }
+
+    #[test]
+    fn access_overview_flags_admins_without_control() {
+        setup();
+        add_admin(principal(5)).unwrap();
+        ic::set_controllers(vec![admin(), principal(9)]);
+
+        let overview = block_on(get_access_overview()).unwrap();
+        assert_eq!(overview.controllers, vec![admin(), principal(9)]);
+        assert_eq!(overview.admins.len(), 2);
+        assert_eq!(overview.providers, vec![provider()]);
+        assert_eq!(overview.admins_not_controllers, vec![principal(5)]);
+
+        ic::set_caller(provider());
+        assert_eq!(block_on(get_access_overview()).err(), Some(MedicalError::Unauthorized));
+    }
+
+    #[test]
+    fn quality_downgrade_scales_by_shortfall() {
+        let config = CanisterConfig {
+            quality_downgrade: Some(QualityDowngradePolicy { quality_threshold: 0.8, strength: 0.5 }),
+            ..CanisterConfig::default()
+        };
+        assert_eq!(apply_quality_downgrade(0.9, 0.8, &config), 0.9);
+        assert_eq!(apply_quality_downgrade(0.9, 0.95, &config), 0.9);
+        assert!((apply_quality_downgrade(0.9, 0.4, &config) - 0.675).abs() < 1e-6);
+        assert!((apply_quality_downgrade(0.9, 0.0, &config) - 0.45).abs() < 1e-6);
+        assert_eq!(apply_quality_downgrade(0.9, 0.0, &CanisterConfig::default()), 0.9);
+
+        let overdriven = CanisterConfig {
+            quality_downgrade: Some(QualityDowngradePolicy { quality_threshold: 0.8, strength: 5.0 }),
+            ..CanisterConfig::default()
+        };
+        assert_eq!(apply_quality_downgrade(0.9, 0.0, &overdriven), 0.0);
+    }
+
+    #[test]
+    fn low_quality_images_are_downgraded_on_analysis() {
+        setup();
+        configure(|c| c.quality_downgrade = Some(QualityDowngradePolicy { quality_threshold: 0.9, strength: 0.6 }));
+
+        let diagnosis = analyze(1, "patient-a");
+        let shortfall = (0.9 - diagnosis.metrics.unwrap().quality_score) / 0.9;
+        assert_eq!(diagnosis.unadjusted_confidence_score, Some(0.78));
+        assert!((diagnosis.confidence_score - 0.78 * (1.0 - 0.6 * shortfall)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn canonical_payload_hashes_to_the_signed_digest() {
+        setup();
+        let diagnosis = analyze(1, "patient-a");
+
+        let payload = get_canonical_payload(diagnosis.id).unwrap();
+        let expected = format!(
+            "v{}|{}|{}|{}|patient-a",
+            PAYLOAD_SCHEMA_VERSION,
+            diagnosis.diagnosis,
+            diagnosis.confidence_bps.map_or(diagnosis.confidence_score.to_string(), |bps| format!("{bps}bps")),
+            diagnosis.timestamp,
+        );
+        assert_eq!(String::from_utf8(payload.clone()).unwrap(), expected);
+
+        let key = VerifyingKey::from_sec1_bytes(&diagnosis.public_key).unwrap();
+        let signature = Signature::from_slice(&diagnosis.signature).unwrap();
+        assert!(key.verify_prehash(&Sha256::digest(&payload), &signature).is_ok());
+        assert_eq!(get_canonical_payload(99), Err(MedicalError::DiagnosisNotFound));
+    }
+
+    #[test]
+    fn upload_sessions_are_capped_per_principal() {
+        setup();
+        configure(|c| c.max_upload_sessions_per_principal = 2);
+        let first = begin_upload().unwrap();
+        begin_upload().unwrap();
+        assert_eq!(begin_upload(), Err(MedicalError::TooManyUploads));
+
+        ic::set_caller(provider());
+        assert!(begin_upload().is_ok());
+
+        ic::set_caller(admin());
+        cancel_upload(first).unwrap();
+        assert!(begin_upload().is_ok());
+    }
+
+    #[test]
+    fn finalized_uploads_free_their_slot() {
+        setup();
+        configure(|c| c.max_upload_sessions_per_principal = 1);
+        let upload_id = begin_upload().unwrap();
+        upload_chunk(upload_id, image(1)[..1024].to_vec()).unwrap();
+        upload_chunk(upload_id, image(1)[1024..].to_vec()).unwrap();
+        assert_eq!(begin_upload(), Err(MedicalError::TooManyUploads));
+
+        let diagnosis = block_on(finalize_upload(upload_id, metadata("patient-a"))).unwrap();
+        assert_eq!(diagnosis.metrics.unwrap().image_size_bytes, 2048);
+        assert!(begin_upload().is_ok());
+    }
+
+    #[test]
+    fn upload_sessions_belong_to_their_owner() {
+        setup();
+        let upload_id = begin_upload().unwrap();
+        ic::set_caller(provider());
+        assert_eq!(upload_chunk(upload_id, vec![0; 16]), Err(MedicalError::UploadNotFound));
+        assert_eq!(cancel_upload(upload_id), Err(MedicalError::UploadNotFound));
+    }
+
+    #[test]
+    fn patient_roster_pages_distinct_ids() {
+        for (id, anonymized_id) in (1..).zip(["carol", "alice", "bob", "alice", "dave", "alice"]) {
+            store_diagnosis(&record(id, anonymized_id));
+        }
+        store_diagnosis(&MedicalDiagnosisResult {
+            status: Some(DiagnosisStatus::Deleted),
+            ..record(7, "erin")
+        });
+
+        let first = get_patient_roster(2, None);
+        assert_eq!(first, vec![("alice".to_string(), 3), ("bob".to_string(), 1)]);
+        let second = get_patient_roster(2, first.last().map(|(id, _)| id.clone()));
+        assert_eq!(second, vec![("carol".to_string(), 1), ("dave".to_string(), 1)]);
+        assert!(get_patient_roster(2, Some("dave".to_string())).is_empty());
+        assert!(get_patient_roster(0, None).is_empty());
+    }
+
+    fn seed_legacy_severities() {
+        store_diagnosis(&MedicalDiagnosisResult {
+            medical_findings: vec![finding("Consolidation", " moderate ", 0.8), finding("Effusion", "MILD", 0.6)],
+            ..record(1, "patient-a")
+        });
+        store_diagnosis(&MedicalDiagnosisResult {
+            medical_findings: vec![finding("Consolidation", "Moderat", 0.8), finding("Effusion", "Mild", 0.6)],
+            ..record(2, "patient-a")
+        });
+        store_diagnosis(&record(3, "patient-a"));
+    }
+
+    fn stored_severities(diagnosis_id: u64) -> Vec<String> {
+        load_diagnosis(diagnosis_id).unwrap().medical_findings.into_iter().map(|f| f.severity).collect()
+    }
+
+    #[test]
+    fn severity_migration_maps_dirty_values_to_unknown() {
+        setup();
+        configure(|c| c.unmapped_severity_policy = UnmappedSeverityPolicy::MapToUnknown);
+        seed_legacy_severities();
+
+        assert_eq!(migrate_severities(0, 10), Ok(SeverityMigrationResult { migrated: 2, next_cursor: None }));
+        assert_eq!(stored_severities(1), vec!["Moderate", "Mild"]);
+        assert_eq!(stored_severities(2), vec!["Unknown", "Mild"]);
+        assert_eq!(stored_severities(3), vec!["Moderate"]);
+        assert!(get_unmapped_severities(10).is_empty());
+    }
+
+    #[test]
+    fn severity_migration_flags_dirty_values_for_review() {
+        setup();
+        configure(|c| c.unmapped_severity_policy = UnmappedSeverityPolicy::FlagForReview);
+        seed_legacy_severities();
+
+        assert_eq!(migrate_severities(0, 10), Ok(SeverityMigrationResult { migrated: 1, next_cursor: None }));
+        assert_eq!(stored_severities(1), vec!["Moderate", "Mild"]);
+        assert_eq!(stored_severities(2), vec!["Moderat", "Mild"]);
+        assert_eq!(get_unmapped_severities(10), vec![2]);
+
+        ic::set_caller(provider());
+        assert_eq!(migrate_severities(0, 10), Err(MedicalError::Unauthorized));
+    }
+
+    #[test]
+    fn severity_migration_resumes_from_cursor() {
+        setup();
+        configure(|c| c.unmapped_severity_policy = UnmappedSeverityPolicy::FlagForReview);
+        seed_legacy_severities();
+
+        assert_eq!(migrate_severities(0, 1), Ok(SeverityMigrationResult { migrated: 1, next_cursor: Some(2) }));
+        assert_eq!(stored_severities(2), vec!["Moderat", "Mild"]);
+        assert_eq!(migrate_severities(2, 1), Ok(SeverityMigrationResult { migrated: 0, next_cursor: Some(3) }));
+        assert_eq!(migrate_severities(3, 1), Ok(SeverityMigrationResult { migrated: 0, next_cursor: None }));
+        assert_eq!(get_unmapped_severities(10), vec![2]);
+    }
+
+    #[test]
+    fn severity_migration_clears_resolved_flags() {
+        setup();
+        configure(|c| c.unmapped_severity_policy = UnmappedSeverityPolicy::FlagForReview);
+        seed_legacy_severities();
+        migrate_severities(0, 10).unwrap();
+        assert_eq!(get_unmapped_severities(10), vec![2]);
+
+        configure(|c| c.unmapped_severity_policy = UnmappedSeverityPolicy::MapToUnknown);
+        migrate_severities(0, 10).unwrap();
+
+        assert_eq!(stored_severities(2), vec!["Unknown", "Mild"]);
+        assert!(get_unmapped_severities(10).is_empty());
+    }
+
+    #[test]
+    fn verification_bundle_verifies_offline() {
+        setup();
+        let diagnosis = analyze(1, "patient-a");
+        ic::advance_time(5);
+
+        let bytes = export_verification_bundle(diagnosis.id).unwrap();
+        let bundle: VerificationBundle = candid::decode_one(&bytes).unwrap();
+        assert_eq!(bundle.diagnosis.id, diagnosis.id);
+        assert_eq!(bundle.canonical_payload, canonical_payload(&diagnosis));
+        assert_eq!(bundle.canister_id, ic::canister_self());
+        assert_eq!(bundle.signature_scheme, "ecdsa-secp256k1-sha256");
+        assert_eq!(bundle.exported_at, ic::START_TIME + 5);
+
+        let key = VerifyingKey::from_sec1_bytes(&bundle.public_key).unwrap();
+        let signature = Signature::from_slice(&bundle.signature).unwrap();
+        assert!(key.verify_prehash(&Sha256::digest(&bundle.canonical_payload), &signature).is_ok());
+        assert_eq!(export_verification_bundle(99).err(), Some(MedicalError::DiagnosisNotFound));
+    }
+
+    #[test]
+    fn reviews_are_assigned_round_robin() {
+        setup();
+        let second = principal(6);
+        add_provider(second).unwrap();
+        configure(|c| c.on_call_reviewers = vec![provider(), principal(7), second]);
+        for id in 1..=4 {
+            store_diagnosis(&record(id, "patient-a"));
+            request_review(id, "Second opinion".to_string()).unwrap();
+        }
+
+        let assigned: Vec<Option<Principal>> = (1..=4).map(|id| load_diagnosis(id).unwrap().assigned_reviewer).collect();
+        assert_eq!(assigned, vec![Some(provider()), Some(second), Some(provider()), Some(second)]);
+
+        ic::set_caller(second);
+        let queue: Vec<u64> = get_my_review_queue().unwrap().into_iter().map(|d| d.id).collect();
+        assert_eq!(queue, vec![2, 4]);
+    }
+
+    #[test]
+    fn reviews_stay_unassigned_without_on_call_providers() {
+        setup();
+        configure(|c| c.on_call_reviewers = vec![principal(7)]);
+        store_diagnosis(&record(1, "patient-a"));
+        request_review(1, "Second opinion".to_string()).unwrap();
+
+        assert_eq!(load_diagnosis(1).unwrap().assigned_reviewer, None);
+        assert!(!get_audit_trail_for_diagnosis(1).iter().any(|entry| entry.action == "REVIEW_ASSIGNED"));
+    }
+
+    #[test]
+    fn compliance_report_for_signed_diagnosis() {
+        setup();
+        configure(|c| c.require_signatures_for_compliance = true);
+        let diagnosis = analyze(1, "patient-a");
+
+        let report = get_fda_compliance_report(diagnosis.id).unwrap();
+        assert!(report.signature_verified);
+        assert_eq!(report.fda_status, "COMPLIANT - FDA 21 CFR Part 820");
+        assert!(report.regulatory_notes.contains(&"Cryptographic signatures ensure data integrity".to_string()));
+    }
+
+    #[test]
+    fn compliance_report_allows_unsigned_by_default() {
+        setup();
+        configure(|c| c.signing_enabled = false);
+        let diagnosis = analyze(1, "patient-a");
+        assert!(diagnosis.signature.is_empty());
+
+        let report = get_fda_compliance_report(diagnosis.id).unwrap();
+        assert!(!report.signature_verified);
+        assert_eq!(report.fda_status, "COMPLIANT - FDA 21 CFR Part 820");
+        assert!(report.regulatory_notes.contains(&"Diagnosis is unsigned; cryptographic signing is currently disabled".to_string()));
+    }
+
+    #[test]
+    fn compliance_report_downgrades_unsigned_when_required() {
+        setup();
+        configure(|c| {
+            c.signing_enabled = false;
+            c.require_signatures_for_compliance = true;
+        });
+        let diagnosis = analyze(1, "patient-a");
+
+        let report = get_fda_compliance_report(diagnosis.id).unwrap();
+        assert!(!report.signature_verified);
+        assert_eq!(report.fda_status, "NON_COMPLIANT - verified signature required");
+
+        configure(|c| c.signing_enabled = true);
+        let report = get_fda_compliance_report(diagnosis.id).unwrap();
+        assert_eq!(report.fda_status, "NON_COMPLIANT - verified signature required");
+        assert!(report.regulatory_notes.contains(&"Diagnosis is unsigned; it was produced while signing was disabled".to_string()));
+    }
+
+    #[test]
+    fn orphan_audit_entries_exclude_known_diagnoses() {
+        setup();
+        let kept = analyze(1, "patient-a").id;
+        let deleted = analyze(2, "patient-a").id;
+        delete_diagnosis(deleted).unwrap();
+        // Purging the record leaves the tombstone, which still accounts for its entries
+        DIAGNOSES.with(|diagnoses| diagnoses.borrow_mut().remove(&deleted));
+        add_audit_entry(0, "CONFIG_UPDATED".to_string(), "Canister-level entry".to_string());
+        add_audit_entry(42, "DIAGNOSIS_CREATED".to_string(), "Never stored".to_string());
+
+        let orphans = find_orphan_audit_entries();
+        assert_eq!(orphans.len(), 1);
+        let orphan = AUDIT_TRAIL.with(|trail| trail.borrow().get(&orphans[0])).unwrap();
+        assert_eq!(orphan.diagnosis_id, 42);
+
+        let archived = DIAGNOSES.with(|diagnoses| diagnoses.borrow_mut().remove(&kept)).unwrap();
+        ARCHIVED_DIAGNOSES.with(|archive| archive.borrow_mut().insert(kept, archived));
+        assert_eq!(find_orphan_audit_entries(), orphans);
+    }
+
+    #[test]
+    fn basis_points_round_trip_exactly() {
+        for bps in 0..=10_000 {
+            assert_eq!(confidence_to_bps(bps_to_confidence(bps)), bps);
+        }
+        assert_eq!(confidence_to_bps(0.78), 7800);
+        assert_eq!(confidence_to_bps(0.123_45), 1235);
+        assert_eq!(confidence_to_bps(-0.5), 0);
+        assert_eq!(confidence_to_bps(1.5), 10_000);
+    }
+
+    #[test]
+    fn scaled_confidence_signatures_verify_reproducibly() {
+        setup();
+        configure(|c| c.scaled_confidence = true);
+        let diagnosis = analyze(1, "patient-a");
+
+        assert_eq!(diagnosis.confidence_bps, Some(7800));
+        assert_eq!(diagnosis.confidence_score, bps_to_confidence(7800));
+        assert!(String::from_utf8(canonical_payload(&diagnosis)).unwrap().contains("|7800bps|"));
+        assert!(verify_stored_signature(&diagnosis));
+
+        // The float form is outside the signed payload, so last-bit drift cannot break it
+        let drifted = MedicalDiagnosisResult {
+            confidence_score: f32::from_bits(diagnosis.confidence_score.to_bits() + 1),
+            ..diagnosis.clone()
+        };
+        assert_eq!(canonical_payload(&drifted), canonical_payload(&diagnosis));
+        assert!(verify_stored_signature(&drifted));
+    }
+
+    #[test]
+    fn lowest_confidence_diagnoses_are_ascending() {
+        for (id, confidence_score) in (1..).zip([0.9, 0.4, 0.7, 0.4, 0.2, 0.95]) {
+            store_diagnosis(&MedicalDiagnosisResult { confidence_score, ..record(id, "patient-a") });
+        }
+        store_diagnosis(&MedicalDiagnosisResult {
+            confidence_score: 0.1,
+            status: Some(DiagnosisStatus::Deleted),
+            ..record(7, "patient-a")
+        });
+
+        let lowest = |n| -> Vec<u64> { get_lowest_confidence_diagnoses(n).into_iter().map(|d| d.id).collect() };
+        assert_eq!(lowest(3), vec![5, 2, 4]);
+        assert_eq!(lowest(10), vec![5, 2, 4, 3, 1, 6]);
+        assert!(lowest(0).is_empty());
+    }
+
+    #[test]
+    fn verification_attestations_are_signed_verdicts() {
+        setup();
+        let diagnosis = analyze(1, "patient-a");
+        store_diagnosis(&MedicalDiagnosisResult { diagnosis: "Tampered".to_string(), ..record(2, "patient-a") });
+        let public_key = ic::public_key(&ecdsa_key_id(&config()).unwrap(), &[]);
+
+        for (diagnosis_id, expected) in [(diagnosis.id, true), (2, false)] {
+            let (verified, attestation) = block_on(attest_verification(diagnosis_id)).unwrap();
+            assert_eq!(verified, expected);
+            assert_eq!(attestation.len(), 17 + 64);
+            let (payload, signature) = attestation.split_at(17);
+            assert_eq!(payload, attestation_payload(diagnosis_id, expected, ic::START_TIME));
+            assert!(verify_data(payload.to_vec(), signature.to_vec(), public_key.clone()));
+        }
+        assert_eq!(block_on(attest_verification(99)).err(), Some(MedicalError::DiagnosisNotFound));
+    }
+
+    #[test]
+    fn dates_normalize_to_canonical_form() {
+        assert_eq!(normalize_date("2024-01-15", DateFormat::IsoDate).as_deref(), Some("2024-01-15"));
+        assert_eq!(normalize_date("20240115", DateFormat::DicomDate).as_deref(), Some("2024-01-15"));
+        assert_eq!(normalize_date("2024-01", DateFormat::YearMonth).as_deref(), Some("2024-01"));
+
+        assert_eq!(normalize_date("20240115", DateFormat::IsoDate), None);
+        assert_eq!(normalize_date("2024-1-15", DateFormat::IsoDate), None);
+        assert_eq!(normalize_date("2024-13-01", DateFormat::IsoDate), None);
+        assert_eq!(normalize_date("20240132", DateFormat::DicomDate), None);
+        assert_eq!(normalize_date("2024/01", DateFormat::YearMonth), None);
+        assert_eq!(normalize_date("2024-00", DateFormat::YearMonth), None);
+        assert_eq!(normalize_date("2024-01-é", DateFormat::IsoDate), None);
+    }
+
+    #[test]
+    fn analysis_enforces_accepted_date_formats() {
+        setup();
+        configure(|c| c.accepted_date_formats = vec![DateFormat::DicomDate, DateFormat::YearMonth]);
+        let analyze_dated = |seed, date: &str| block_on(analyze_medical_image(image(seed), dated(date)));
+
+        let stored = analyze_dated(1, " 20240115 ").unwrap();
+        assert_eq!(stored.patient_metadata.acquisition_date, "2024-01-15");
+        assert_eq!(analyze_dated(2, "2024-02").unwrap().patient_metadata.acquisition_date, "2024-02");
+        assert_eq!(analyze_dated(3, "2024-01-15").err(), Some(MedicalError::InvalidDateFormat));
+        assert_eq!(analyze_dated(4, "Jan 15 2024").err(), Some(MedicalError::InvalidDateFormat));
+    }
+
+    #[test]
+    fn config_snapshot_signature_covers_config_and_time() {
+        setup();
+        ic::set_caller(outsider());
+        let (snapshot, timestamp, signature) = block_on(get_signed_config_snapshot()).unwrap();
+        let public_key = ic::public_key(&ecdsa_key_id(&snapshot).unwrap(), &[]);
+
+        assert_eq!(timestamp, ic::START_TIME);
+        assert!(verify_data(config_snapshot_payload(&snapshot, timestamp), signature.clone(), public_key.clone()));
+        assert!(!verify_data(config_snapshot_payload(&snapshot, timestamp + 1), signature.clone(), public_key.clone()));
+        let altered = CanisterConfig { dedup_ttl_ns: 0, ..snapshot };
+        assert!(!verify_data(config_snapshot_payload(&altered, timestamp), signature, public_key));
+    }
+
+    #[test]
+    fn diagnosis_ids_survive_a_counter_reset() {
+        setup();
+        let first = analyze(1, "patient-a").id;
+        let second = analyze(2, "patient-a").id;
+        delete_diagnosis(second).unwrap();
+        DIAGNOSES.with(|diagnoses| diagnoses.borrow_mut().remove(&second));
+
+        // A cell left at its default, as on canisters upgraded before it was persisted
+        NEXT_DIAGNOSIS_ID.with(|next| next.borrow_mut().set(1).unwrap());
+        let third = analyze(3, "patient-a").id;
+        assert_eq!((first, second, third), (1, 2, 3));
+        assert!(load_diagnosis(first).is_ok());
+        assert_eq!(load_diagnosis(third).unwrap().status(), DiagnosisStatus::Active);
+        assert!(!is_deleted(third));
+    }
+
+    #[test]
+    fn deletion_tombstones_the_diagnosis_once() {
+        setup();
+        let diagnosis_id = analyze(1, "patient-a").id;
+        let deletions = || get_audit_trail_for_diagnosis(diagnosis_id).iter().filter(|e| e.action == "DIAGNOSIS_DELETED").count();
+
+        assert!(!is_deleted(diagnosis_id));
+        delete_diagnosis(diagnosis_id).unwrap();
+        assert!(is_deleted(diagnosis_id));
+        assert_eq!(load_diagnosis(diagnosis_id).unwrap().status(), DiagnosisStatus::Deleted);
+        assert_eq!(deletions(), 1);
+
+        assert_eq!(delete_diagnosis(diagnosis_id), Ok(()));
+        assert_eq!(deletions(), 1);
+
+        assert_eq!(delete_diagnosis(99), Err(MedicalError::DiagnosisNotFound));
+        assert!(!is_deleted(99));
+    }
+
+    #[test]
+    fn consensus_keeps_strict_majority_findings() {
+        let per_model = vec![
+            vec![finding("Effusion", "Mild", 0.6), finding("Nodule", "Moderate", 0.9), finding("Consolidation", "Severe", 0.8)],
+            vec![finding("effusion ", "Moderate", 0.8), finding("Consolidation", "Severe", 0.7)],
+            vec![finding("Consolidation", "Moderate", 0.9)],
+        ];
+
+        let consolidated = consolidate_findings(&per_model);
+        let summary: Vec<(&str, Option<u32>, &str)> = consolidated.iter()
+            .map(|f| (f.finding.as_str(), f.agreement_count, f.severity.as_str()))
+            .collect();
+        assert_eq!(summary, vec![("Consolidation", Some(3), "Moderate"), ("effusion ", Some(2), "Moderate")]);
+        assert!((consolidated[0].confidence - 0.8).abs() < 1e-6);
+        assert!((consolidated[1].confidence - 0.7).abs() < 1e-6);
+
+        // Half of an even model count is not a majority
+        let split = vec![vec![finding("Nodule", "Mild", 0.5)], vec![finding("Nodule", "Mild", 0.5)], vec![], vec![]];
+        assert!(consolidate_findings(&split).is_empty());
+        assert!(consolidate_findings(&[]).is_empty());
+    }
+
+    #[test]
+    fn consensus_counts_repeated_findings_once_per_model() {
+        let repeated = vec![
+            vec![finding("Nodule", "Mild", 0.4), finding("Nodule", "Severe", 0.9)],
+            vec![],
+            vec![],
+        ];
+        assert!(consolidate_findings(&repeated).is_empty());
+
+        let agreed = vec![
+            vec![finding("Nodule", "Mild", 0.4), finding("Nodule", "Severe", 0.9)],
+            vec![finding("Nodule", "Moderate", 0.5)],
+        ];
+        let consolidated = consolidate_findings(&agreed);
+        assert_eq!(consolidated.len(), 1);
+        assert_eq!(consolidated[0].agreement_count, Some(2));
+        assert_eq!(consolidated[0].severity, "Severe");
+        assert!((consolidated[0].confidence - 0.7).abs() < 1e-6);
+    }
+
+    #[test]
+    fn consensus_rejects_too_many_models() {
+        let per_model = vec![vec![finding("Nodule", "Mild", 0.5)]; MAX_CONSENSUS_MODELS as usize + 1];
+        assert_eq!(compute_finding_agreement(per_model).err(), Some(MedicalError::BatchTooLarge(MAX_CONSENSUS_MODELS)));
+    }
+
+    #[test]
+    fn audit_ids_continue_from_the_stored_trail() {
+        setup();
+        add_audit_entry(1, "DIAGNOSIS_CREATED".to_string(), "first".to_string());
+        add_audit_entry(1, "DIAGNOSIS_REVIEWED".to_string(), "second".to_string());
+        add_audit_entry(1, "DIAGNOSIS_AMENDED".to_string(), "third".to_string());
+
+        let ids: Vec<u64> = get_medical_audit_trail().iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(get_medical_audit_trail()[0].details, "first");
+        assert_eq!(verify_audit_chain(), None);
+    }
+
+    #[test]
+    fn newest_audit_entry_can_be_amended_within_grace() {
+        setup();
+        configure(|c| c.audit_amendment_grace_ns = 60_000_000_000);
+        add_audit_entry(1, "DIAGNOSIS_CREATED".to_string(), "typo".to_string());
+        ic::advance_time(30_000_000_000);
+
+        assert_eq!(amend_audit_entry(1, "corrected".to_string()), Ok(()));
+        let entry = AUDIT_TRAIL.with(|trail| trail.borrow().get(&1)).unwrap();
+        assert_eq!(entry.details, "corrected");
+        assert!(entry.compliance_flags.contains(&format!("AMENDED_BY:{}", admin())));
+
+        add_audit_entry(1, "DIAGNOSIS_REVIEWED".to_string(), "next".to_string());
+        assert_eq!(verify_audit_chain(), None);
+    }
+
+    #[test]
+    fn audit_entries_are_immutable_once_chained_or_expired() {
+        setup();
+        configure(|c| c.audit_amendment_grace_ns = 60_000_000_000);
+        add_audit_entry(1, "DIAGNOSIS_CREATED".to_string(), "first".to_string());
+        add_audit_entry(1, "DIAGNOSIS_REVIEWED".to_string(), "second".to_string());
+
+        assert_eq!(amend_audit_entry(1, "edited".to_string()), Err(MedicalError::AuditEntryImmutable));
+        assert_eq!(amend_audit_entry(3, "edited".to_string()), Err(MedicalError::AuditEntryNotFound));
+        ic::advance_time(60_000_000_001);
+        assert_eq!(amend_audit_entry(2, "edited".to_string()), Err(MedicalError::AuditEntryImmutable));
+
+        configure(|c| c.audit_amendment_grace_ns = 0);
+        add_audit_entry(1, "DIAGNOSIS_AMENDED".to_string(), "third".to_string());
+        assert_eq!(amend_audit_entry(3, "edited".to_string()), Err(MedicalError::AuditEntryImmutable));
+        assert_eq!(get_medical_audit_trail()[0].details, "first");
+    }
+
+    #[test]
+    fn amendment_during_signing_discards_the_stale_signature() {
+        setup();
+        configure(|c| {
+            c.sign_audit_entries = true;
+            c.audit_amendment_grace_ns = 60_000_000_000;
+        });
+        add_audit_entry(1, "DIAGNOSIS_CREATED".to_string(), "typo".to_string());
+        ic::interleave_next_sign(|| amend_audit_entry(1, "corrected".to_string()).unwrap());
+
+        ic::run_spawned();
+        let entry = AUDIT_TRAIL.with(|trail| trail.borrow().get(&1)).unwrap();
+        assert_eq!(entry.details, "corrected");
+        assert_eq!(entry.entry_signature, None);
+
+        ic::run_spawned();
+        assert_eq!(verify_audit_entry(1), Ok(true));
+    }
+
+    #[test]
+    fn diagnoses_missing_codes_are_listed() {
+        store_diagnosis(&record(1, "patient-a"));
+        store_diagnosis(&MedicalDiagnosisResult { icd10_code: None, snomed_code: None, ..record(2, "patient-a") });
+        store_diagnosis(&MedicalDiagnosisResult { snomed_code: None, ..record(3, "patient-a") });
+        store_diagnosis(&MedicalDiagnosisResult {
+            icd10_code: None,
+            status: Some(DiagnosisStatus::Deleted),
+            ..record(4, "patient-a")
+        });
+
+        assert_eq!(find_diagnoses_missing_codes(), vec![2, 3]);
+        assert_eq!(diagnosis_codes("Abnormal opacity"), None);
+        assert_eq!(diagnosis_codes("Pneumonia detected"), Some(("J18.9".to_string(), "233604007".to_string())));
+    }
+
+    #[test]
+    fn repeated_audit_entries_coalesce_behind_a_marker() {
+        setup();
+        configure(|c| {
+            c.max_audit_entries_per_diagnosis = Some(2);
+            c.audit_coalesce_window_ns = 60_000_000_000;
+        });
+        for _ in 0..5 {
+            add_audit_entry(1, "DIAGNOSIS_ACCESSED".to_string(), "viewed".to_string());
+            add_audit_entry(0, "DATA_SIGNED".to_string(), "detached".to_string());
+        }
+        add_audit_entry(1, "REVIEW_REQUESTED".to_string(), "second opinion".to_string());
+
+        let actions = |diagnosis_id| -> Vec<String> {
+            get_audit_trail_for_diagnosis(diagnosis_id).into_iter().map(|entry| entry.action).collect()
+        };
+        assert_eq!(actions(1), vec!["DIAGNOSIS_ACCESSED", "DIAGNOSIS_ACCESSED", "AUDIT_SUPPRESSED", "REVIEW_REQUESTED"]);
+        assert_eq!(actions(0).len(), 5);
+
+        ic::advance_time(60_000_000_001);
+        add_audit_entry(1, "DIAGNOSIS_ACCESSED".to_string(), "viewed later".to_string());
+        assert_eq!(actions(1).last().map(String::as_str), Some("DIAGNOSIS_ACCESSED"));
+        assert_eq!(verify_audit_chain(), None);
+    }
+
+    #[test]
+    fn key_consistency_flags_records_from_other_keys() {
+        setup();
+        let first = analyze(1, "patient-a").id;
+        let second = analyze(2, "patient-b").id;
+        store_diagnosis(&record(3, "patient-c"));
+        assert_eq!(block_on(audit_key_consistency()), Ok(vec![]));
+
+        let original = config();
+        configure(|c| c.ecdsa_key_name = "key_1".to_string());
+        assert_eq!(block_on(audit_key_consistency()), Ok(vec![first, second]));
+
+        configure(|c| {
+            c.ecdsa_key_name = original.ecdsa_key_name.clone();
+            c.per_patient_key_derivation = !original.per_patient_key_derivation;
+        });
+        assert_eq!(block_on(audit_key_consistency()), Ok(vec![first, second]));
+
+        ic::set_caller(provider());
+        assert_eq!(block_on(audit_key_consistency()), Err(MedicalError::Unauthorized));
+    }
+
+    #[test]
+    fn anonymized_id_change_re_signs_and_is_audited() {
+        setup();
+        configure(|c| c.per_patient_key_derivation = true);
+        let diagnosis = analyze(1, "patient-a");
+
+        block_on(update_patient_metadata(diagnosis.id, metadata("patient-b"))).unwrap();
+        let updated = load_diagnosis(diagnosis.id).unwrap();
+        assert_ne!(updated.signature, diagnosis.signature);
+        assert_eq!(updated.public_key, ic::public_key(&ecdsa_key_id(&config()).unwrap(), &[b"patient-b".to_vec()]));
+        assert!(verify_stored_signature(&updated));
+        let entry = get_audit_trail_for_diagnosis(diagnosis.id).pop().unwrap();
+        assert_eq!(entry.action, "METADATA_UPDATED");
+        assert!(entry.details.ends_with(", anonymized_id changed, re-signed)"));
+
+        // Dedup entries follow the record to its new ID
+        assert_eq!(analyze(1, "patient-b").id, diagnosis.id);
+        assert_ne!(analyze(1, "patient-a").id, diagnosis.id);
+    }
+
+    #[test]
+    fn other_metadata_changes_keep_the_signature() {
+        setup();
+        let diagnosis = analyze(1, "patient-a");
+
+        let corrected = PatientMetadata { study_type: "Chest CT".to_string(), ..metadata("patient-a") };
+        block_on(update_patient_metadata(diagnosis.id, corrected)).unwrap();
+        let updated = load_diagnosis(diagnosis.id).unwrap();
+        assert_eq!(updated.patient_metadata.study_type, "Chest CT");
+        assert_eq!(updated.signature, diagnosis.signature);
+        assert!(verify_stored_signature(&updated));
+        let entry = get_audit_trail_for_diagnosis(diagnosis.id).pop().unwrap();
+        assert_eq!(entry.action, "METADATA_UPDATED");
+        assert!(!entry.details.contains("re-signed"));
+    }
+
+    #[test]
+    fn concurrent_id_change_aborts_metadata_update() {
+        setup();
+        let id = analyze(1, "patient-a").id;
+        ic::interleave_next_sign(move || block_on(update_patient_metadata(id, metadata("patient-c"))).unwrap());
+
+        assert_eq!(
+            block_on(update_patient_metadata(id, metadata("patient-b"))),
+            Err(MedicalError::ConcurrentModification),
+        );
+        let stored = load_diagnosis(id).unwrap();
+        assert_eq!(stored.patient_metadata.anonymized_id, "patient-c");
+        assert!(verify_stored_signature(&stored));
+    }
+
+    #[test]
+    fn concurrent_id_change_aborts_amendment() {
+        setup();
+        let id = analyze(1, "patient-a").id;
+        ic::interleave_next_sign(move || block_on(update_patient_metadata(id, metadata("patient-c"))).unwrap());
+
+        assert_eq!(
+            block_on(amend_diagnosis(id, "Normal chest radiograph".to_string(), "Misread".to_string())).err(),
+            Some(MedicalError::ConcurrentModification),
+        );
+        assert_eq!(status_of(id), DiagnosisStatus::Active);
+        assert!(verify_stored_signature(&load_diagnosis(id).unwrap()));
+    }
+
+    #[test]
+    fn escalations_notify_the_channel_for_each_severity() {
+        setup();
+        let (pager, ward, oncology) = (principal(20), principal(21), principal(22));
+        ic::set_unreachable(ward);
+        configure(|c| c.escalation_channels = vec![
+            EscalationChannel { severity: Severity::Severe, target: pager },
+            EscalationChannel { severity: Severity::Mild, target: ward },
+            EscalationChannel { severity: Severity::Critical, target: oncology },
+        ]);
+        let diagnosis = MedicalDiagnosisResult {
+            medical_findings: vec![
+                finding("Consolidation", "Severe", 0.9),
+                finding("Effusion", "Mild", 0.6),
+                finding("Pneumothorax", "Severe", 0.8),
+            ],
+            ..record(1, "patient-a")
+        };
+
+        dispatch_escalations(&diagnosis, &config());
+
+        let sent = ic::sent();
+        assert_eq!(sent.len(), 1);
+        let (target, method, arg) = &sent[0];
+        assert_eq!((*target, method.as_str()), (pager, "notify_escalation"));
+        let notice: EscalationNotice = candid::decode_one(arg).unwrap();
+        assert_eq!(notice.diagnosis_id, 1);
+        assert_eq!(notice.severity, Severity::Severe);
+        assert_eq!(notice.findings, vec!["Consolidation (Right lower lobe)", "Pneumothorax (Right lower lobe)"]);
+
+        let actions: Vec<String> = get_audit_trail_for_diagnosis(1).into_iter().map(|entry| entry.action).collect();
+        assert_eq!(actions, vec!["ESCALATION_DISPATCHED", "ESCALATION_FAILED"]);
+    }
+
+    fn percentiles(samples: &[u64]) -> (u64, u64, u64) {
+        let percentiles = latency_percentiles(samples);
+        (percentiles.p50_ms, percentiles.p90_ms, percentiles.p99_ms)
+    }
+
+    #[test]
+    fn latency_percentiles_use_nearest_rank() {
+        assert_eq!(percentiles(&[]), (0, 0, 0));
+        assert_eq!(percentiles(&[7]), (7, 7, 7));
+        assert_eq!(percentiles(&[1, 2, 3, 4]), (2, 4, 4));
+        let hundred: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentiles(&hundred), (50, 90, 99));
+        let thousand: Vec<u64> = (1..=1000).collect();
+        assert_eq!(percentiles(&thousand), (500, 900, 990));
+    }
+
+    #[test]
+    fn processing_percentiles_cover_records_with_metrics() {
+        setup();
+        for seed in 1..=3 {
+            analyze(seed, "patient-a");
+        }
+        store_diagnosis(&record(10, "patient-a"));
+
+        let report = get_processing_time_percentiles();
+        assert_eq!(report.sample_count, 3);
+        let metrics = load_diagnosis(1).unwrap().metrics.unwrap();
+        assert_eq!(report.model_inference_time.p50_ms, metrics.model_inference_time_ms);
+        assert_eq!(report.processing_time.p99_ms, metrics.processing_time_ms);
+    }
+
+    #[test]
+    fn old_diagnoses_move_to_the_archive() {
+        setup();
+        configure(|c| c.archive_after_days = Some(30));
+        let aged = ic::START_TIME - 31 * NANOS_PER_DAY;
+        store_diagnosis(&MedicalDiagnosisResult { timestamp: aged, ..record(1, "patient-a") });
+        store_diagnosis(&MedicalDiagnosisResult {
+            timestamp: aged,
+            status: Some(DiagnosisStatus::UnderReview),
+            ..record(2, "patient-a")
+        });
+        store_diagnosis(&MedicalDiagnosisResult { timestamp: aged, ..record(3, "patient-a") });
+        store_diagnosis(&record(4, "patient-a"));
+
+        assert_eq!(archive_old_diagnoses(1), Ok(1));
+        assert_eq!(archive_old_diagnoses(10), Ok(1));
+        assert_eq!(archive_old_diagnoses(10), Ok(0));
+
+        let hot: Vec<u64> = DIAGNOSES.with(|diagnoses| diagnoses.borrow().iter().map(|(id, _)| id).collect());
+        assert_eq!(hot, vec![2, 4]);
+        assert_eq!(get_archived_diagnosis(1).map(|d| d.id), Some(1));
+        assert_eq!(get_archived_diagnosis(4).map(|d| d.id), None);
+        assert_eq!(verify_with_key(3, Vec::new()), Ok(false));
+        assert_eq!(get_medical_audit_trail().iter().filter(|e| e.action == "DIAGNOSES_ARCHIVED").count(), 2);
+    }
+
+    #[test]
+    fn archival_requires_a_configured_age() {
+        setup();
+        assert_eq!(
+            archive_old_diagnoses(10),
+            Err(MedicalError::InvalidConfig("archive_after_days is not set".to_string())),
+        );
+        ic::set_caller(provider());
+        assert_eq!(archive_old_diagnoses(10), Err(MedicalError::Unauthorized));
+    }
+
+    #[test]
+    fn verify_with_key_uses_the_supplied_key() {
+        setup();
+        configure(|c| c.per_patient_key_derivation = true);
+        let diagnosis = analyze(1, "patient-a");
+        let key_id = ecdsa_key_id(&config()).unwrap();
+
+        assert_eq!(verify_with_key(diagnosis.id, ic::public_key(&key_id, &[b"patient-a".to_vec()])), Ok(true));
+        assert_eq!(verify_with_key(diagnosis.id, ic::public_key(&key_id, &[b"patient-b".to_vec()])), Ok(false));
+        assert_eq!(verify_with_key(diagnosis.id, vec![0x02; 33]), Ok(false));
+
+        assert_eq!(verify_with_key(99, Vec::new()), Err(MedicalError::DiagnosisNotFound));
+    }
+
+    #[test]
+    fn version_one_records_decode_and_verify() {
+        let key_id = ecdsa_key_id(&config()).unwrap();
+        let payload = format!("Pneumonia detected in right lower lobe|0.87|{}|legacy-patient", ic::START_TIME);
+        let signed = block_on(ic::sign_with_ecdsa(&SignWithEcdsaArgs {
+            message_hash: Sha256::digest(payload.as_bytes()).to_vec(),
+            derivation_path: Vec::new(),
+            key_id: key_id.clone(),
+        }))
+        .unwrap();
+        let legacy = LegacyDiagnosis {
+            signature: signed.signature,
+            public_key: ic::public_key(&key_id, &[]),
+            ..legacy_diagnosis(7)
+        };
+
+        let decoded = MedicalDiagnosisResult::from_bytes(Cow::Owned(candid::encode_one(legacy).unwrap()));
+        assert_eq!(decoded.payload_schema_version, None);
+        assert_eq!(decoded.payload_schema_version(), LEGACY_PAYLOAD_SCHEMA_VERSION);
+        store_diagnosis(&decoded);
+
+        assert_eq!(get_canonical_payload(7), Ok(payload.into_bytes()));
+        assert_eq!(verify_diagnosis_signature(7), Ok(true));
+        assert_eq!(verify_with_key(7, ic::public_key(&key_id, &[])), Ok(true));
+    }
+
+    #[test]
+    fn failing_update_endpoints_record_errors() {
+        setup();
+        ic::set_caller(outsider());
+        assert!(block_on(sign_data(b"payload".to_vec())).is_err());
+        assert!(update_config(config()).is_err());
+        ic::set_caller(provider());
+        assert!(block_on(submit_review(99, ReviewDecision::Confirmed, String::new())).is_err());
+        assert!(delete_diagnosis(99).is_err());
+        assert!(record_consent_batch(vec![consent("")]).iter().all(Result::is_err));
+
+        assert!(get_fda_compliance_report(99).is_err());
+        ic::set_caller(Principal::anonymous());
+        assert!(get_fda_compliance_report(1).is_err());
+
+        ic::set_caller(admin());
+        assert!(import_diagnosis(record(5, "patient-a")).is_err());
+        let recorded: Vec<(String, MedicalError)> = get_recent_errors(10).unwrap()
+            .into_iter()
+            .map(|event| (event.endpoint, event.error))
+            .collect();
+        assert_eq!(recorded, vec![
+            ("import_diagnosis".to_string(), MedicalError::InvalidSignature),
+            ("get_fda_compliance_report".to_string(), MedicalError::AnonymousCaller),
+            ("get_fda_compliance_report".to_string(), MedicalError::DiagnosisNotFound),
+            ("record_consent_batch".to_string(), MedicalError::InvalidConsent("anonymized_id is empty".to_string())),
+            ("delete_diagnosis".to_string(), MedicalError::DiagnosisNotFound),
+            ("submit_review".to_string(), MedicalError::DiagnosisNotFound),
+            ("update_config".to_string(), MedicalError::Unauthorized),
+            ("sign_data".to_string(), MedicalError::Unauthorized),
+        ]);
+    }
+
+    #[test]
+    fn recent_errors_evict_the_oldest_first() {
+        setup();
+        for diagnosis_id in 1..=MAX_RECENT_ERRORS as u64 + 5 {
+            ic::advance_time(1);
+            assert!(delete_diagnosis(diagnosis_id).is_err());
+        }
+
+        let errors = get_recent_errors(u64::MAX).unwrap();
+        assert_eq!(errors.len(), MAX_RECENT_ERRORS);
+        assert_eq!(errors.first().map(|e| e.timestamp), Some(ic::START_TIME + MAX_RECENT_ERRORS as u64 + 5));
+        assert_eq!(errors.last().map(|e| e.timestamp), Some(ic::START_TIME + 6));
+        assert_eq!(get_recent_errors(3).unwrap().len(), 3);
+
+        ic::set_caller(provider());
+        assert_eq!(get_recent_errors(3).err(), Some(MedicalError::Unauthorized));
+    }
+
+    #[test]
+    fn signing_curve_selects_the_threshold_key() {
+        setup();
+        let diagnosis = analyze(1, "patient-a");
+
+        assert_eq!(diagnosis.signing_curve, Some(SigningCurve::Secp256k1));
+        let expected = EcdsaKeyId { curve: EcdsaCurve::Secp256k1, name: "dfx_test_key".to_string() };
+        assert!(!ic::key_ids().is_empty());
+        assert!(ic::key_ids().iter().all(|key_id| *key_id == expected));
+
+        // Records from before the curve was recorded were all secp256k1
+        let legacy = MedicalDiagnosisResult { signing_curve: None, ..record(2, "patient-a") };
+        assert_eq!(legacy.signing_curve(), SigningCurve::Secp256k1);
+    }
+
+    #[test]
+    fn unsupported_curves_are_rejected() {
+        setup();
+        let unsupported = CanisterConfig { signing_curve: SigningCurve::Secp256r1, ..config() };
+        assert_eq!(
+            update_config(unsupported),
+            Err(MedicalError::InvalidConfig("secp256r1 threshold ECDSA is not available on the IC".to_string())),
+        );
+
+        configure(|c| c.signing_curve = SigningCurve::Secp256r1);
+        assert!(matches!(
+            block_on(analyze_medical_image(image(1), metadata("patient-a"))).err(),
+            Some(MedicalError::SignatureFailed(_)),
+        ));
+        assert!(ic::key_ids().is_empty());
+
+        let diagnosis = MedicalDiagnosisResult { signing_curve: Some(SigningCurve::Secp256r1), ..record(1, "patient-a") };
+        assert!(!verify_stored_signature(&diagnosis));
+    }
+
+    #[test]
+    fn patient_timeline_is_chronological_with_gaps() {
+        let at = |days: u64| ic::START_TIME + days * NANOS_PER_DAY;
+        store_diagnosis(&MedicalDiagnosisResult { timestamp: at(10), ..record(1, "patient-a") });
+        store_diagnosis(&MedicalDiagnosisResult { timestamp: at(0), ..record(2, "patient-a") });
+        store_diagnosis(&MedicalDiagnosisResult { timestamp: at(3), ..record(3, "patient-b") });
+        store_diagnosis(&MedicalDiagnosisResult {
+            timestamp: at(5),
+            status: Some(DiagnosisStatus::Deleted),
+            ..record(4, "patient-a")
+        });
+        store_diagnosis(&MedicalDiagnosisResult { timestamp: at(10), ..record(5, "patient-a") });
+
+        let timeline: Vec<(u64, Option<u64>)> = get_patient_timeline("patient-a".to_string())
+            .into_iter()
+            .map(|entry| (entry.diagnosis.id, entry.since_previous_ns))
+            .collect();
+        assert_eq!(timeline, vec![(2, None), (1, Some(10 * NANOS_PER_DAY)), (5, Some(0))]);
+        assert!(get_patient_timeline("patient-z".to_string()).is_empty());
+    }
+
+    fn oversized_record() -> MedicalDiagnosisResult {
+        let severities = ["Mild", "Critical", "Moderate", "Severe"];
+        let medical_findings = (0..200)
+            .map(|i| finding(&format!("Finding {i:03} {}", "x".repeat(40)), severities[i % 4], (i % 10) as f32 / 10.0))
+            .collect();
+        MedicalDiagnosisResult { medical_findings, ..record(1, "patient-a") }
+    }
+
+    #[test]
+    fn oversized_findings_are_rejected_by_default() {
+        let config = CanisterConfig::default();
+        let mut diagnosis = oversized_record();
+        assert_eq!(fit_findings(&mut diagnosis, &config), Err(MedicalError::ResultTooLarge));
+        assert_eq!(diagnosis.medical_findings.len(), 200);
+
+        let mut small = record(2, "patient-a");
+        assert_eq!(fit_findings(&mut small, &config), Ok(0));
+    }
+
+    #[test]
+    fn oversized_findings_truncate_to_the_most_severe() {
+        let config = CanisterConfig {
+            oversized_findings_policy: OversizedFindingsPolicy::TruncateToFit,
+            ..CanisterConfig::default()
+        };
+        let mut diagnosis = oversized_record();
+
+        let dropped = fit_findings(&mut diagnosis, &config).unwrap();
+        let kept = &diagnosis.medical_findings;
+        assert!(dropped > 0);
+        assert_eq!(kept.len() + dropped, 200);
+        assert!(diagnosis.to_bytes().len() <= MAX_DIAGNOSIS_BYTES as usize);
+        assert!(kept.windows(2).all(|pair| {
+            let (a, b) = (Severity::priority(&pair[0].severity), Severity::priority(&pair[1].severity));
+            a > b || (a == b && pair[0].confidence >= pair[1].confidence)
+        }));
+        assert_eq!(kept[0].severity, "Critical");
+        assert_eq!(kept[0].confidence, 0.9);
+
+        let mut hopeless = MedicalDiagnosisResult {
+            diagnosis: "x".repeat(MAX_DIAGNOSIS_BYTES as usize),
+            ..oversized_record()
+        };
+        assert_eq!(fit_findings(&mut hopeless, &config), Err(MedicalError::ResultTooLarge));
+    }
+
+    fn node(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
+        Sha256::new().chain_update(left).chain_update(right).finalize().into()
+    }
+
+    #[test]
+    fn merkle_root_pairs_and_duplicates_odd_nodes() {
+        let (a, b, c) = ([1; 32], [2; 32], [3; 32]);
+        assert_eq!(merkle_root(vec![]), [0; 32]);
+        assert_eq!(merkle_root(vec![a]), a);
+        assert_eq!(merkle_root(vec![a, b]), node(a, b));
+        assert_eq!(merkle_root(vec![a, b, c]), node(node(a, b), node(c, c)));
+        assert_ne!(merkle_root(vec![b, a]), merkle_root(vec![a, b]));
+    }
+
+    #[test]
+    fn integrity_fingerprint_tracks_writes_only() {
+        setup();
+        let diagnosis = analyze(1, "patient-a");
+        let fingerprint = get_integrity_fingerprint();
+
+        // Reads leave every input untouched
+        get_diagnosis(diagnosis.id, None);
+        get_patient_timeline("patient-a".to_string());
+        assert!(verify_audit_chain().is_none());
+        assert_eq!(get_integrity_fingerprint(), fingerprint);
+
+        let mut tampered = diagnosis.clone();
+        tampered.confidence_score = 0.99;
+        store_diagnosis(&tampered);
+        let after_tamper = get_integrity_fingerprint();
+        assert_ne!(after_tamper, fingerprint);
+
+        store_diagnosis(&diagnosis);
+        assert_eq!(get_integrity_fingerprint(), fingerprint);
+        add_audit_entry(diagnosis.id, "DIAGNOSIS_ACCESSED".to_string(), "viewed".to_string());
+        assert_ne!(get_integrity_fingerprint(), fingerprint);
+        let after_audit = get_integrity_fingerprint();
+        configure(|c| c.dedup_ttl_ns = 0);
+        assert_ne!(get_integrity_fingerprint(), after_audit);
+    }
+
+    #[test]
+    fn oversized_review_text_is_rejected_before_signing() {
+        setup();
+        let id = analyze(1, "patient-a").id;
+        request_review(id, "Second opinion".to_string()).unwrap();
+        let entries = audit_trail_len();
+        let too_long = Some(MedicalError::TextTooLong(MAX_FREE_TEXT_BYTES as u64));
+
+        let note = "n".repeat(4096);
+        assert_eq!(block_on(submit_review(id, ReviewDecision::Confirmed, note)).err(), too_long);
+        assert_eq!(
+            block_on(amend_diagnosis(id, "d".repeat(MAX_FREE_TEXT_BYTES + 1), "Misread".to_string())).err(),
+            too_long,
+        );
+        assert_eq!(block_on(amend_diagnosis(id, "Normal".to_string(), "r".repeat(4096))).err(), too_long);
+        assert_eq!(request_review(id, "r".repeat(4096)).err(), too_long);
+        assert_eq!(audit_trail_len(), entries);
+        assert_eq!(status_of(id), DiagnosisStatus::UnderReview);
+
+        let note = "n".repeat(MAX_FREE_TEXT_BYTES);
+        assert_eq!(block_on(submit_review(id, ReviewDecision::Confirmed, note)), Ok(()));
+    }
+}
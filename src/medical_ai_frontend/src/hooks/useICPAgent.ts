@@ -104,13 +104,51 @@ const idlFactory = ({ IDL }: any) => {
     'generated_timestamp': IDL.Nat64,
   });
   
-  const Result = IDL.Variant({ 'Ok': MedicalDiagnosisResult, 'Err': IDL.Text });
+  const DiagnosisStatus = IDL.Variant({
+    'Active': IDL.Null,
+    'UnderReview': IDL.Null,
+    'Amended': IDL.Null,
+    'Superseded': IDL.Null,
+    'Deleted': IDL.Null,
+  });
+  
+  const MedicalError = IDL.Variant({
+    'Unauthorized': IDL.Null,
+    'RateLimited': IDL.Null,
+    'SignatureFailed': IDL.Text,
+    'DiagnosisNotFound': IDL.Null,
+    'InvalidStatusTransition': DiagnosisStatus,
+    'InvalidImage': IDL.Text,
+    'InvalidAcquisitionDate': IDL.Null,
+    'InvalidConfig': IDL.Text,
+    'InvalidSignature': IDL.Null,
+    'BatchTooLarge': IDL.Nat64,
+    'AnonymousCaller': IDL.Null,
+    'SigningDisabled': IDL.Null,
+    'ReviewNotFound': IDL.Null,
+    'InvalidConsent': IDL.Text,
+    'AuditEntryNotFound': IDL.Null,
+    'InconsistentFindings': IDL.Text,
+    'WeakAnonymization': IDL.Null,
+    'UnregisteredModel': IDL.Text,
+    'NotNormalDiagnosis': IDL.Null,
+    'ManagementCallFailed': IDL.Text,
+    'UploadNotFound': IDL.Null,
+    'TooManyUploads': IDL.Null,
+    'InvalidDateFormat': IDL.Null,
+    'AuditEntryImmutable': IDL.Null,
+    'ResultTooLarge': IDL.Null,
+    'ConcurrentModification': IDL.Null,
+    'TextTooLong': IDL.Nat64,
+  });
+  
+  const Result = IDL.Variant({ 'Ok': MedicalDiagnosisResult, 'Err': MedicalError });
   const Result_1 = IDL.Variant({ 'Ok': IDL.Bool, 'Err': IDL.Text });
   const Result_2 = IDL.Variant({ 'Ok': ComplianceReport, 'Err': IDL.Text });
   
   return IDL.Service({
     'analyze_medical_image': IDL.Func([IDL.Vec(IDL.Nat8), PatientMetadata], [Result], []),
-    'get_diagnosis': IDL.Func([IDL.Nat64], [IDL.Opt(MedicalDiagnosisResult)], ['query']),
+    'get_diagnosis': IDL.Func([IDL.Nat64, IDL.Opt(IDL.Bool)], [IDL.Opt(MedicalDiagnosisResult)], ['query']),
     'get_all_diagnoses': IDL.Func([], [IDL.Vec(MedicalDiagnosisResult)], ['query']),
     'get_medical_audit_trail': IDL.Func([], [IDL.Vec(MedicalAuditEntry)], ['query']),
     'get_audit_trail_for_diagnosis': IDL.Func([IDL.Nat64], [IDL.Vec(MedicalAuditEntry)], ['query']),
@@ -237,7 +275,8 @@ export const useICPAgent = () => {
     try {
       const result = await actor.analyze_medical_image(Array.from(imageData), patientMetadata);
       if ('Err' in result) {
-        throw new Error(result.Err);
+        const [kind, detail] = Object.entries(result.Err)[0];
+        throw new Error(typeof detail === 'string' ? `${kind}: ${detail}` : kind);
       }
       return result.Ok;
     } catch (error) {
@@ -250,7 +289,7 @@ export const useICPAgent = () => {
     if (!actor) throw new Error('Actor not initialized');
     
     try {
-      const result = await actor.get_diagnosis(diagnosisId);
+      const result = await actor.get_diagnosis(diagnosisId, []);
       return result[0] || null;
     } catch (error) {
       console.error('Error getting diagnosis:', error);